@@ -1,6 +1,38 @@
 use image::{Rgba, RgbaImage};
-use napi::{bindgen_prelude::AsyncTask, Env, Error, Task};
-use std::collections::HashMap;
+use napi::{
+  bindgen_prelude::{AbortSignal, AsyncTask, Either, JsObjectValue, JsValue},
+  Env, Error, Status, Task,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{
+  atomic::{AtomicBool, Ordering},
+  Arc,
+};
+
+pub mod codes;
+pub mod colour;
+pub mod compositing;
+pub mod drawing;
+pub mod feature_pack;
+pub mod fill;
+pub mod golden;
+pub mod hough;
+pub mod keypoints;
+pub mod motion;
+pub mod statistics;
+pub mod tracking;
+
+use codes::AsyncDecodeCodes;
+use colour::{resolve_colour, resolve_optional_colour, Colour};
+use compositing::CompositeOptions;
+use drawing::{StrokeOptions, TextOptions};
+use fill::{AsyncMeasureFill, FillDirection, FillOptions};
+use hough::{AsyncDetectCircles, AsyncDetectLines, HoughCircleOptions, HoughLineOptions};
+use keypoints::{AsyncDetectKeypoints, AsyncLocateImage, KeypointOptions, LocateImageOptions};
+use statistics::AsyncGetRegionStatistics;
+
+const MAX_BLANK_IMAGE_BYTES: u64 = 1 << 30;
 
 #[napi(object)]
 pub struct FeatureMatch {
@@ -9,6 +41,59 @@ pub struct FeatureMatch {
   pub y: u32,
 }
 
+#[napi(object)]
+#[derive(Clone, Copy, Debug)]
+pub struct Point {
+  pub x: f64,
+  pub y: f64,
+}
+
+/// Where and when an image was captured.
+#[napi(object)]
+#[derive(Clone, Copy, Debug)]
+pub struct CaptureInfo {
+  /// Milliseconds since the Unix epoch.
+  pub timestamp: f64,
+  pub window_id: i64,
+  /// Screen position of the image's top-left pixel.
+  pub screen_x: i32,
+  pub screen_y: i32,
+}
+
+#[napi(object)]
+#[derive(Clone, Copy, Debug)]
+pub struct Region {
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+}
+
+impl Region {
+  /// The part of an optional region that lies within an image, defaulting to
+  /// the whole image.
+  pub fn within(region: Option<Region>, rgba_image: &RgbaImage) -> Region {
+    match region {
+      Some(region) => {
+        let x = region.x.min(rgba_image.width());
+        let y = region.y.min(rgba_image.height());
+        Region {
+          x,
+          y,
+          width: region.width.min(rgba_image.width() - x),
+          height: region.height.min(rgba_image.height() - y),
+        }
+      }
+      None => Region {
+        x: 0,
+        y: 0,
+        width: rgba_image.width(),
+        height: rgba_image.height(),
+      },
+    }
+  }
+}
+
 #[napi(object)]
 #[derive(Clone)]
 pub struct Pixel {
@@ -18,8 +103,201 @@ pub struct Pixel {
 }
 
 #[napi(object)]
+#[derive(Clone)]
 pub struct Feature {
   pub pixels: Vec<Pixel>,
+  /// Point to target relative to the feature, in the same coordinate space as
+  /// its pixels. It may lie outside the feature. Defaults to the centre of the
+  /// feature's bounding box.
+  pub anchor: Option<FeatureAnchor>,
+}
+
+#[napi(object)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct FeatureAnchor {
+  pub x: i64,
+  pub y: i64,
+}
+
+#[napi(object)]
+pub struct FeatureLocation {
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+  /// Anchor position in the image, which may lie outside it when the anchor
+  /// is outside the feature.
+  pub anchor_x: i64,
+  pub anchor_y: i64,
+  /// Fractional top-left position, only present when `subpixel` matching was
+  /// requested.
+  pub subpixel_x: Option<f64>,
+  pub subpixel_y: Option<f64>,
+  /// Rotation in degrees, clockwise, of the orientation that matched.
+  pub angle: f64,
+  /// Whether the matched orientation was mirrored horizontally before rotating.
+  pub reflected: bool,
+}
+
+#[napi(object)]
+#[derive(Clone, Copy, Default)]
+pub struct SearchOptions {
+  pub limit: Option<u32>,
+  pub first_only: Option<bool>,
+  pub count_only: Option<bool>,
+}
+
+#[napi(object)]
+#[derive(Clone, Copy, Default)]
+pub struct FeatureSearchOptions {
+  pub limit: Option<u32>,
+  pub first_only: Option<bool>,
+  pub count_only: Option<bool>,
+  /// Refines matches to fractional positions. Only matches that fit at least
+  /// as well as their direct neighbours are kept, so a cluster of adjacent
+  /// matches is reported once.
+  pub subpixel: Option<bool>,
+  /// Rotation range to search, in degrees clockwise. A missing bound defaults
+  /// to 0, so `maxAngle: 30` searches from 0 to 30 degrees and only the
+  /// unrotated feature is searched when neither is given. Ranges with more
+  /// than 3600 angles are rejected.
+  pub min_angle: Option<f64>,
+  pub max_angle: Option<f64>,
+  pub angle_step: Option<f64>,
+  pub include_reflections: Option<bool>,
+}
+
+/// Either the matches of a search or, in `countOnly` mode, how many there were.
+pub type SearchResult<T> = Either<Vec<T>, u32>;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchLimit {
+  max_results: Option<usize>,
+  count_only: bool,
+}
+
+impl SearchLimit {
+  fn new(limit: Option<u32>, first_only: Option<bool>, count_only: Option<bool>) -> Self {
+    let max_results = if first_only.unwrap_or(false) {
+      Some(1)
+    } else {
+      limit.map(|limit| limit as usize)
+    };
+
+    Self {
+      max_results,
+      count_only: count_only.unwrap_or(false),
+    }
+  }
+
+  fn is_reached(&self, found: usize) -> bool {
+    self
+      .max_results
+      .is_some_and(|max_results| found >= max_results)
+  }
+
+  fn matches<T>(self) -> SearchMatches<T> {
+    SearchMatches {
+      limit: self,
+      results: Vec::new(),
+      count: 0,
+    }
+  }
+}
+
+/// Collects the matches of a search. In `countOnly` mode they are only
+/// counted, so nothing is built or kept for them.
+pub struct SearchMatches<T> {
+  limit: SearchLimit,
+  results: Vec<T>,
+  count: usize,
+}
+
+impl<T> SearchMatches<T> {
+  /// Records a match, building it only if results are kept. Returns whether
+  /// the search limit has been reached, which with a limit of 0 happens
+  /// before anything is recorded.
+  fn add(&mut self, build: impl FnOnce() -> T) -> bool {
+    if self.limit.is_reached(self.count) {
+      return true;
+    }
+    if !self.limit.count_only {
+      self.results.push(build());
+    }
+    self.count += 1;
+    self.limit.is_reached(self.count)
+  }
+
+  fn into_vec(self) -> Vec<T> {
+    self.results
+  }
+
+  fn map<U>(self, f: impl FnMut(T) -> U) -> SearchMatches<U> {
+    SearchMatches {
+      limit: self.limit,
+      results: self.results.into_iter().map(f).collect(),
+      count: self.count,
+    }
+  }
+
+  fn into_result(self) -> SearchResult<T> {
+    if self.limit.count_only {
+      Either::B(self.count as u32)
+    } else {
+      Either::A(self.results)
+    }
+  }
+}
+
+impl From<Option<SearchOptions>> for SearchLimit {
+  fn from(value: Option<SearchOptions>) -> Self {
+    let options = value.unwrap_or_default();
+    SearchLimit::new(options.limit, options.first_only, options.count_only)
+  }
+}
+
+impl From<Option<FeatureSearchOptions>> for SearchLimit {
+  fn from(value: Option<FeatureSearchOptions>) -> Self {
+    let options = value.unwrap_or_default();
+    SearchLimit::new(options.limit, options.first_only, options.count_only)
+  }
+}
+
+/// Tracks whether the `AbortSignal` passed to an async method has fired, so
+/// that work which has already started can stop at its next checkpoint.
+#[derive(Clone, Debug, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+  pub fn from_signal(signal: Option<&AbortSignal>) -> Self {
+    let cancellation = Cancellation::default();
+
+    if let Some(signal) = signal {
+      let aborted = cancellation.0.clone();
+      signal.on_abort(move || aborted.store(true, Ordering::Relaxed));
+    }
+
+    cancellation
+  }
+
+  pub fn check(&self) -> Result<(), Error> {
+    if self.0.load(Ordering::Relaxed) {
+      Err(Error::from_reason("The operation was aborted"))
+    } else {
+      Ok(())
+    }
+  }
+
+  /// Rejects with an `AbortError` when the signal stopped the task part-way
+  /// through, as napi does for a task aborted before it started.
+  pub fn reject<T>(&self, env: &Env, err: Error) -> Result<T, Error> {
+    if !self.0.load(Ordering::Relaxed) {
+      return Err(err);
+    }
+    let mut error = env.create_error(Error::new(Status::Cancelled, "AbortError".to_owned()))?;
+    error.set_named_property("name", "AbortError")?;
+    Err(Error::from(error.to_unknown()))
+  }
 }
 
 #[napi(object)]
@@ -32,6 +310,7 @@ pub struct ColourFrequency {
 #[derive(Debug, Clone)]
 pub struct Image {
   rgba_image: RgbaImage,
+  capture_info: Option<CaptureInfo>,
   #[napi(readonly)]
   pub width: u32,
   #[napi(readonly)]
@@ -40,6 +319,102 @@ pub struct Image {
 
 #[napi]
 impl Image {
+  /// Creates an image of the given size filled with a single colour, which
+  /// defaults to transparent. Images over 1 GiB are rejected.
+  #[napi(factory)]
+  pub fn blank(width: u32, height: u32, rgba: Option<Colour>) -> Result<Image, Error> {
+    let byte_count = u64::from(width) * u64::from(height) * 4;
+    if byte_count > MAX_BLANK_IMAGE_BYTES || usize::try_from(byte_count).is_err() {
+      return Err(Error::from_reason(format!(
+        "A {width}x{height} image is too large"
+      )));
+    }
+
+    let rgba = resolve_optional_colour(rgba)?.unwrap_or(0);
+    Ok(Image::from(RgbaImage::from_pixel(
+      width,
+      height,
+      rgba_number_into_rgba(rgba),
+    )))
+  }
+
+  /// Copies a region of the image into a new image. Capture metadata is kept,
+  /// moved to the screen position of the region.
+  #[napi]
+  pub fn crop(&self, region: Region) -> Image {
+    let region = Region::within(Some(region), &self.rgba_image);
+    let cropped = Image::from(
+      image::imageops::crop_imm(
+        &self.rgba_image,
+        region.x,
+        region.y,
+        region.width,
+        region.height,
+      )
+      .to_image(),
+    );
+
+    match self.capture_info {
+      Some(capture_info) => cropped.with_capture_info(CaptureInfo {
+        screen_x: capture_info.screen_x.saturating_add_unsigned(region.x),
+        screen_y: capture_info.screen_y.saturating_add_unsigned(region.y),
+        ..capture_info
+      }),
+      None => cropped,
+    }
+  }
+
+  /// Composites another image onto this one with its top-left at the given
+  /// position.
+  #[napi]
+  pub fn paste(&mut self, source: &Image, x: i32, y: i32, options: Option<CompositeOptions>) {
+    compositing::paste(
+      &mut self.rgba_image,
+      &source.rgba_image,
+      (x.into(), y.into()),
+      options,
+    );
+  }
+
+  /// Fills a region, or the whole image, with a colour.
+  #[napi]
+  pub fn fill(
+    &mut self,
+    rgba: Colour,
+    region: Option<Region>,
+    options: Option<CompositeOptions>,
+  ) -> Result<(), Error> {
+    compositing::fill(&mut self.rgba_image, region, resolve_colour(rgba)?, options);
+    Ok(())
+  }
+
+  /// Where and when the image was captured, if it came from a window.
+  #[napi]
+  pub fn capture_info(&self) -> Option<CaptureInfo> {
+    self.capture_info
+  }
+
+  /// Maps a position in the image to screen coordinates, using the window's
+  /// position at the time of capture.
+  #[napi]
+  pub fn to_screen(&self, x: f64, y: f64) -> Result<Point, Error> {
+    let capture_info = self.require_capture_info()?;
+    Ok(Point {
+      x: x + f64::from(capture_info.screen_x),
+      y: y + f64::from(capture_info.screen_y),
+    })
+  }
+
+  /// Maps a screen position to a position in the image.
+  #[napi]
+  pub fn from_screen(&self, x: f64, y: f64) -> Result<Point, Error> {
+    let capture_info = self.require_capture_info()?;
+    Ok(Point {
+      x: x - f64::from(capture_info.screen_x),
+      y: y - f64::from(capture_info.screen_y),
+    })
+  }
+
   #[napi(ts_return_type = "Promise<number>")]
   pub fn get_pixel_rgba(&self, x: u32, y: u32) -> AsyncTask<AsyncGetPixelRgba> {
     AsyncTask::new(AsyncGetPixelRgba::new(x, y, self.rgba_image.clone()))
@@ -50,44 +425,99 @@ impl Image {
     AsyncGetPixelRgba::new(x, y, self.rgba_image.clone()).compute()
   }
 
-  #[napi(ts_return_type = "Promise<Array<Pixel>>")]
-  pub fn find_rgbas(&self, rgba_number: u32, max_color_distance_percent: f64) -> AsyncTask<AsyncFindRgbas> {
-    AsyncTask::new(AsyncFindRgbas::new(
-      rgba_number,
-      self.rgba_image.clone(),
-      max_color_distance_percent,
+  #[napi(ts_return_type = "Promise<Array<Pixel> | number>")]
+  pub fn find_rgbas(
+    &self,
+    rgba: Colour,
+    max_color_distance_percent: f64,
+    options: Option<SearchOptions>,
+    signal: Option<AbortSignal>,
+  ) -> Result<AsyncTask<AsyncFindRgbas>, Error> {
+    let rgba_number = resolve_colour(rgba)?;
+    let cancellation = Cancellation::from_signal(signal.as_ref());
+    Ok(AsyncTask::with_optional_signal(
+      AsyncFindRgbas::new(
+        rgba_number,
+        self.rgba_image.clone(),
+        max_color_distance_percent,
+        options.into(),
+        cancellation,
+      ),
+      signal,
     ))
   }
 
-  #[napi(ts_return_type = "Promise<Array<FeatureMatch>>")]
+  #[napi(ts_return_type = "Promise<Array<FeatureMatch> | number>")]
   pub fn get_features_from_color(
     &self,
-    rgba_number: u32,
+    rgba: Colour,
     max_color_distance_percent: f64,
     max_grouping_distance: u32,
-  ) -> AsyncTask<AsyncGetFeaturesFromColor> {
-    AsyncTask::new(AsyncGetFeaturesFromColor::new(
-      rgba_number,
-      self.rgba_image.clone(),
-      max_color_distance_percent,
-      max_grouping_distance,
+    options: Option<SearchOptions>,
+    signal: Option<AbortSignal>,
+  ) -> Result<AsyncTask<AsyncGetFeaturesFromColor>, Error> {
+    let rgba_number = resolve_colour(rgba)?;
+    let cancellation = Cancellation::from_signal(signal.as_ref());
+    Ok(AsyncTask::with_optional_signal(
+      AsyncGetFeaturesFromColor::new(
+        rgba_number,
+        self.rgba_image.clone(),
+        max_color_distance_percent,
+        max_grouping_distance,
+        options.into(),
+        cancellation,
+      ),
+      signal,
     ))
   }
 
-  #[napi(ts_return_type = "Promise<Array<Pixel>>")]
+  /// Returns the top-left pixel of each match. `locateFeature` also reports
+  /// each match's size, anchor and orientation.
+  #[napi(ts_return_type = "Promise<Array<Pixel> | number>")]
   pub fn find_feature(
     &self,
     feature: Feature,
     max_color_distance_percent: f64,
     max_pixel_difference_percent: f64,
-  ) -> AsyncTask<AsyncFindFeatures> {
-    AsyncTask::new(AsyncFindFeatures::new(
-      feature,
-      max_color_distance_percent,
-      max_pixel_difference_percent,
-      self.width,
-      self.height,
-      self.rgba_image.clone(),
+    options: Option<FeatureSearchOptions>,
+    signal: Option<AbortSignal>,
+  ) -> Result<AsyncTask<AsyncFindFeatureTopLefts>, Error> {
+    let cancellation = Cancellation::from_signal(signal.as_ref());
+    Ok(AsyncTask::with_optional_signal(
+      AsyncFindFeatureTopLefts(AsyncFindFeatures::new(
+        feature,
+        max_color_distance_percent,
+        max_pixel_difference_percent,
+        self.rgba_image.clone(),
+        options,
+        cancellation,
+      )?),
+      signal,
+    ))
+  }
+
+  /// Like `findFeature`, but returns the bounding box, anchor and orientation
+  /// of each match.
+  #[napi(ts_return_type = "Promise<Array<FeatureLocation> | number>")]
+  pub fn locate_feature(
+    &self,
+    feature: Feature,
+    max_color_distance_percent: f64,
+    max_pixel_difference_percent: f64,
+    options: Option<FeatureSearchOptions>,
+    signal: Option<AbortSignal>,
+  ) -> Result<AsyncTask<AsyncFindFeatures>, Error> {
+    let cancellation = Cancellation::from_signal(signal.as_ref());
+    Ok(AsyncTask::with_optional_signal(
+      AsyncFindFeatures::new(
+        feature,
+        max_color_distance_percent,
+        max_pixel_difference_percent,
+        self.rgba_image.clone(),
+        options,
+        cancellation,
+      )?,
+      signal,
     ))
   }
 
@@ -132,15 +562,274 @@ impl Image {
     start_y: u32,
     end_x: u32,
     end_y: u32,
+    signal: Option<AbortSignal>,
   ) -> AsyncTask<AsyncGetColourFrequencies> {
-    AsyncTask::new(AsyncGetColourFrequencies::new(
-      start_x,
-      start_y,
-      end_x,
-      end_y,
-      self.rgba_image.clone(),
+    let cancellation = Cancellation::from_signal(signal.as_ref());
+    AsyncTask::with_optional_signal(
+      AsyncGetColourFrequencies::new(
+        start_x,
+        start_y,
+        end_x,
+        end_y,
+        self.rgba_image.clone(),
+        cancellation,
+      ),
+      signal,
+    )
+  }
+
+  /// Fraction of a bar or gauge that is filled with a colour, measured from
+  /// the edge of the region the fill grows from to the furthest filled row or
+  /// column. Unfilled rows or columns before the fill count as a border, and
+  /// the far end is assumed to have one as wide.
+  #[napi(ts_return_type = "Promise<number>")]
+  pub fn measure_fill(
+    &self,
+    region: Region,
+    rgba: Colour,
+    direction: FillDirection,
+    max_color_distance_percent: f64,
+    options: Option<FillOptions>,
+    signal: Option<AbortSignal>,
+  ) -> Result<AsyncTask<AsyncMeasureFill>, Error> {
+    let cancellation = Cancellation::from_signal(signal.as_ref());
+    Ok(AsyncTask::with_optional_signal(
+      AsyncMeasureFill::new(
+        self.rgba_image.clone(),
+        region,
+        resolve_colour(rgba)?,
+        direction,
+        max_color_distance_percent,
+        options,
+        cancellation,
+      ),
+      signal,
+    ))
+  }
+
+  /// Per-channel mean, standard deviation, range and histogram of a region,
+  /// or of the whole image.
+  #[napi(ts_return_type = "Promise<RegionStatistics>")]
+  pub fn get_region_statistics(
+    &self,
+    region: Option<Region>,
+    signal: Option<AbortSignal>,
+  ) -> AsyncTask<AsyncGetRegionStatistics> {
+    let cancellation = Cancellation::from_signal(signal.as_ref());
+    AsyncTask::with_optional_signal(
+      AsyncGetRegionStatistics::new(self.rgba_image.clone(), region, cancellation),
+      signal,
+    )
+  }
+
+  /// Straight lines found with a Hough transform over the image's edges.
+  #[napi(ts_return_type = "Promise<Array<HoughLine>>")]
+  pub fn detect_lines(
+    &self,
+    options: Option<HoughLineOptions>,
+    signal: Option<AbortSignal>,
+  ) -> AsyncTask<AsyncDetectLines> {
+    let cancellation = Cancellation::from_signal(signal.as_ref());
+    AsyncTask::with_optional_signal(
+      AsyncDetectLines::new(self.rgba_image.clone(), options, cancellation),
+      signal,
+    )
+  }
+
+  /// Circles with radii in the given range found with a Hough transform over
+  /// the image's edges. Radii beyond the image's diagonal are not searched.
+  #[napi(ts_return_type = "Promise<Array<HoughCircle>>")]
+  pub fn detect_circles(
+    &self,
+    min_radius: u32,
+    max_radius: u32,
+    options: Option<HoughCircleOptions>,
+    signal: Option<AbortSignal>,
+  ) -> Result<AsyncTask<AsyncDetectCircles>, Error> {
+    let cancellation = Cancellation::from_signal(signal.as_ref());
+    Ok(AsyncTask::with_optional_signal(
+      AsyncDetectCircles::new(
+        self.rgba_image.clone(),
+        min_radius,
+        max_radius,
+        options,
+        cancellation,
+      )?,
+      signal,
     ))
   }
+
+  #[napi(ts_return_type = "Promise<Array<Keypoint>>")]
+  pub fn detect_keypoints(
+    &self,
+    options: Option<KeypointOptions>,
+    signal: Option<AbortSignal>,
+  ) -> AsyncTask<AsyncDetectKeypoints> {
+    let cancellation = Cancellation::from_signal(signal.as_ref());
+    AsyncTask::with_optional_signal(
+      AsyncDetectKeypoints::new(self.rgba_image.clone(), options, cancellation),
+      signal,
+    )
+  }
+
+  #[napi(ts_return_type = "Promise<ImageLocation | null>")]
+  pub fn locate_image(
+    &self,
+    reference: &Image,
+    options: Option<LocateImageOptions>,
+    signal: Option<AbortSignal>,
+  ) -> AsyncTask<AsyncLocateImage> {
+    let cancellation = Cancellation::from_signal(signal.as_ref());
+    AsyncTask::with_optional_signal(
+      AsyncLocateImage::new(
+        reference.rgba_image.clone(),
+        self.rgba_image.clone(),
+        options,
+        cancellation,
+      ),
+      signal,
+    )
+  }
+
+  /// Finds and decodes QR codes, Data Matrix codes and EAN, UPC and Code 128
+  /// barcodes, optionally only within a region of the image.
+  #[napi(ts_return_type = "Promise<Array<DecodedCode>>")]
+  pub fn decode_codes(&self, region: Option<Region>) -> AsyncTask<AsyncDecodeCodes> {
+    AsyncTask::new(AsyncDecodeCodes::new(self.rgba_image.clone(), region))
+  }
+
+  /// Writes the image to a file, in the format implied by its extension.
+  #[napi(ts_return_type = "Promise<void>")]
+  pub fn save(&self, path: String) -> AsyncTask<AsyncSaveImage> {
+    AsyncTask::new(AsyncSaveImage {
+      path,
+      rgba_image: self.rgba_image.clone(),
+    })
+  }
+
+  #[napi]
+  pub fn draw_line(
+    &mut self,
+    start_x: i32,
+    start_y: i32,
+    end_x: i32,
+    end_y: i32,
+    rgba: Colour,
+    options: Option<StrokeOptions>,
+  ) -> Result<(), Error> {
+    drawing::draw_line(
+      &mut self.rgba_image,
+      (start_x.into(), start_y.into()),
+      (end_x.into(), end_y.into()),
+      resolve_colour(rgba)?,
+      options,
+    );
+    Ok(())
+  }
+
+  #[napi]
+  pub fn draw_rectangle(
+    &mut self,
+    region: Region,
+    rgba: Colour,
+    options: Option<StrokeOptions>,
+  ) -> Result<(), Error> {
+    drawing::draw_rectangle(&mut self.rgba_image, region, resolve_colour(rgba)?, options);
+    Ok(())
+  }
+
+  #[napi]
+  pub fn draw_circle(
+    &mut self,
+    centre_x: i32,
+    centre_y: i32,
+    radius: u32,
+    rgba: Colour,
+    options: Option<StrokeOptions>,
+  ) -> Result<(), Error> {
+    drawing::draw_circle(
+      &mut self.rgba_image,
+      (centre_x.into(), centre_y.into()),
+      radius,
+      resolve_colour(rgba)?,
+      options,
+    );
+    Ok(())
+  }
+
+  #[napi]
+  pub fn draw_crosshair(
+    &mut self,
+    x: i32,
+    y: i32,
+    size: u32,
+    rgba: Colour,
+    options: Option<StrokeOptions>,
+  ) -> Result<(), Error> {
+    drawing::draw_crosshair(
+      &mut self.rgba_image,
+      (x.into(), y.into()),
+      size,
+      resolve_colour(rgba)?,
+      options,
+    );
+    Ok(())
+  }
+
+  #[napi]
+  pub fn draw_text(
+    &mut self,
+    x: i32,
+    y: i32,
+    text: String,
+    rgba: Colour,
+    options: Option<TextOptions>,
+  ) -> Result<(), Error> {
+    let options = options.unwrap_or_default();
+    drawing::draw_text(
+      &mut self.rgba_image,
+      (x.into(), y.into()),
+      &text,
+      resolve_colour(rgba)?,
+      options.scale.unwrap_or(1),
+      resolve_optional_colour(options.background)?,
+    );
+    Ok(())
+  }
+
+  #[napi]
+  pub fn draw_pixels(&mut self, pixels: Vec<Pixel>, rgba: Option<Colour>) -> Result<(), Error> {
+    let rgba = resolve_optional_colour(rgba)?;
+    drawing::draw_pixels(&mut self.rgba_image, &pixels, (0, 0), rgba);
+    Ok(())
+  }
+
+  #[napi]
+  pub fn draw_feature_match(
+    &mut self,
+    feature_match: FeatureMatch,
+    rgba: Option<Colour>,
+  ) -> Result<(), Error> {
+    let rgba = resolve_optional_colour(rgba)?;
+    drawing::draw_pixels(
+      &mut self.rgba_image,
+      &feature_match.feature.pixels,
+      (feature_match.x.into(), feature_match.y.into()),
+      rgba,
+    );
+    Ok(())
+  }
+
+  #[napi]
+  pub fn overlay_mask(&mut self, mask: &Image, x: i32, y: i32, rgba: Colour) -> Result<(), Error> {
+    drawing::overlay_mask(
+      &mut self.rgba_image,
+      &mask.rgba_image,
+      (x.into(), y.into()),
+      resolve_colour(rgba)?,
+    );
+    Ok(())
+  }
 }
 
 impl From<RgbaImage> for Image {
@@ -149,10 +838,46 @@ impl From<RgbaImage> for Image {
       width: value.width(),
       height: value.height(),
       rgba_image: value,
+      capture_info: None,
     }
   }
 }
 
+impl Image {
+  pub fn with_capture_info(mut self, capture_info: CaptureInfo) -> Self {
+    self.capture_info = Some(capture_info);
+    self
+  }
+
+  fn require_capture_info(&self) -> Result<CaptureInfo, Error> {
+    self
+      .capture_info
+      .ok_or_else(|| Error::from_reason("The image was not captured from a window"))
+  }
+}
+
+pub struct AsyncSaveImage {
+  path: String,
+  rgba_image: RgbaImage,
+}
+
+#[napi]
+impl Task for AsyncSaveImage {
+  type Output = ();
+  type JsValue = ();
+
+  fn compute(&mut self) -> Result<Self::Output, Error> {
+    self
+      .rgba_image
+      .save(&self.path)
+      .map_err(|error| Error::from_reason(error.to_string()))
+  }
+
+  fn resolve(&mut self, _env: Env, _output: Self::Output) -> Result<Self::JsValue, Error> {
+    Ok(())
+  }
+}
+
 pub fn rgba_into_rgba_number(rgba: &Rgba<u8>) -> u32 {
   ((rgba.0[0] as u32) << 24)
     | ((rgba.0[1] as u32) << 16)
@@ -169,6 +894,18 @@ pub fn rgba_number_into_rgba(rgba_number: u32) -> Rgba<u8> {
   ])
 }
 
+/// Offset of the minimum of the parabola through three equally spaced samples,
+/// relative to the centre sample and limited to half a step either side.
+fn quadratic_peak_offset(before: f64, centre: f64, after: f64) -> f64 {
+  let curvature = before - 2.0 * centre + after;
+
+  if curvature <= f64::EPSILON {
+    return 0.0;
+  }
+
+  (0.5 * (before - after) / curvature).clamp(-0.5, 0.5)
+}
+
 fn color_distance(color1_u32: u32, color2_u32: u32, use_alpha: bool) -> f64 {
   let rgba1 = rgba_number_into_rgba(color1_u32);
   let rgba2 = rgba_number_into_rgba(color2_u32);
@@ -220,47 +957,124 @@ pub struct AsyncFindRgbas {
   rgba_number: u32,
   rgba_image: RgbaImage,
   max_color_distance_percent: f64,
+  search_limit: SearchLimit,
+  cancellation: Cancellation,
 }
 
 impl AsyncFindRgbas {
-  pub fn new(rgba_number: u32, rgba_image: RgbaImage, max_color_distance_percent: f64) -> Self {
+  pub fn new(
+    rgba_number: u32,
+    rgba_image: RgbaImage,
+    max_color_distance_percent: f64,
+    search_limit: SearchLimit,
+    cancellation: Cancellation,
+  ) -> Self {
     Self {
       rgba_number,
       rgba_image,
       max_color_distance_percent,
+      search_limit,
+      cancellation,
     }
   }
-}
-
-#[napi]
-impl Task for AsyncFindRgbas {
-  type Output = Vec<Pixel>;
-  type JsValue = Vec<Pixel>;
 
-  fn compute(&mut self) -> Result<Self::Output, Error> {
+  fn find_pixels(&self) -> Result<SearchMatches<Pixel>, Error> {
     const MAX_COLOR_DISTANCE: f64 = 510.0; // Using alpha: sqrt(255^2 * 4)
     let actual_color_tolerance_value = MAX_COLOR_DISTANCE * self.max_color_distance_percent;
-    let mut positions = Vec::new();
+    let mut positions = self.search_limit.matches();
 
     for (x, y, pixel) in self.rgba_image.enumerate_pixels() {
+      if x == 0 {
+        self.cancellation.check()?;
+      }
+
       let pixel_rgba_u32 = rgba_into_rgba_number(pixel);
       let distance = color_distance(self.rgba_number, pixel_rgba_u32, true);
 
       if distance <= actual_color_tolerance_value {
-        positions.push(Pixel {
+        let limit_reached = positions.add(|| Pixel {
           x,
           y,
-          rgba: rgba_into_rgba_number(pixel),
+          rgba: pixel_rgba_u32,
         });
+
+        if limit_reached {
+          break;
+        }
       }
     }
 
     Ok(positions)
   }
+}
+
+#[napi]
+impl Task for AsyncFindRgbas {
+  type Output = SearchResult<Pixel>;
+  type JsValue = SearchResult<Pixel>;
+
+  fn compute(&mut self) -> Result<Self::Output, Error> {
+    Ok(self.find_pixels()?.into_result())
+  }
 
   fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
     Ok(output)
   }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue, Error> {
+    self.cancellation.reject(&env, err)
+  }
+}
+
+/// Groups pixels that lie within `max_grouping_distance` of each other,
+/// transitively, ordered by the scan position of each group's first pixel.
+fn group_pixels(
+  pixels: Vec<Pixel>,
+  max_grouping_distance: u32,
+  cancellation: &Cancellation,
+) -> Result<Vec<Vec<Pixel>>, Error> {
+  let max_dist_sq: i64 = (max_grouping_distance as i64) * (max_grouping_distance as i64);
+
+  let mut parent: Vec<usize> = (0..pixels.len()).collect();
+  fn find_set(i: usize, parent: &mut Vec<usize>) -> usize {
+    if parent[i] == i {
+      return i;
+    }
+    parent[i] = find_set(parent[i], parent);
+    parent[i]
+  }
+  fn unite_sets(i: usize, j: usize, parent: &mut Vec<usize>) {
+    let i_id = find_set(i, parent);
+    let j_id = find_set(j, parent);
+    if i_id != j_id {
+      parent[j_id] = i_id;
+    }
+  }
+
+  for i in 0..pixels.len() {
+    cancellation.check()?;
+
+    for j in (i + 1)..pixels.len() {
+      let dx = (pixels[i].x as i64) - (pixels[j].x as i64);
+      let dy = (pixels[i].y as i64) - (pixels[j].y as i64);
+      if dx * dx + dy * dy <= max_dist_sq {
+        unite_sets(i, j, &mut parent);
+      }
+    }
+  }
+
+  let mut groups: HashMap<usize, Vec<Pixel>> = HashMap::new();
+  for (i, pixel) in pixels.into_iter().enumerate() {
+    let root = find_set(i, &mut parent);
+    groups.entry(root).or_default().push(pixel);
+  }
+
+  // Groups are reported in scan order of their first pixel so that limited
+  // searches are deterministic.
+  let mut groups: Vec<Vec<Pixel>> = groups.into_values().collect();
+  groups.sort_by_key(|group| (group[0].y, group[0].x));
+
+  Ok(groups)
 }
 
 pub struct AsyncGetFeaturesFromColor {
@@ -268,6 +1082,8 @@ pub struct AsyncGetFeaturesFromColor {
   rgba_image: RgbaImage,
   max_color_distance_percent: f64,
   max_grouping_distance: u32,
+  search_limit: SearchLimit,
+  cancellation: Cancellation,
 }
 
 impl AsyncGetFeaturesFromColor {
@@ -276,66 +1092,43 @@ impl AsyncGetFeaturesFromColor {
     rgba_image: RgbaImage,
     max_color_distance_percent: f64,
     max_grouping_distance: u32,
+    search_limit: SearchLimit,
+    cancellation: Cancellation,
   ) -> Self {
     Self {
       rgba_number,
       rgba_image,
       max_color_distance_percent,
       max_grouping_distance,
+      search_limit,
+      cancellation,
     }
   }
 }
 
 #[napi]
 impl Task for AsyncGetFeaturesFromColor {
-  type Output = Vec<FeatureMatch>;
-  type JsValue = Vec<FeatureMatch>;
+  type Output = SearchResult<FeatureMatch>;
+  type JsValue = SearchResult<FeatureMatch>;
 
   fn compute(&mut self) -> Result<Self::Output, Error> {
+    // Every matching pixel can join any group, so grouping needs the full set of
+    // pixels and the limit is only applied to the groups produced.
     let pixels = AsyncFindRgbas::new(
       self.rgba_number,
       self.rgba_image.clone(),
       self.max_color_distance_percent,
+      SearchLimit::default(),
+      self.cancellation.clone(),
     )
-    .compute()?;
-
-    let max_dist_sq: i64 = (self.max_grouping_distance as i64) * (self.max_grouping_distance as i64);
+    .find_pixels()?
+    .into_vec();
 
-    let mut parent: Vec<usize> = (0..pixels.len()).collect();
-    fn find_set(i: usize, parent: &mut Vec<usize>) -> usize {
-      if parent[i] == i {
-        return i;
-      }
-      parent[i] = find_set(parent[i], parent);
-      parent[i]
-    }
-    fn unite_sets(i: usize, j: usize, parent: &mut Vec<usize>) {
-      let i_id = find_set(i, parent);
-      let j_id = find_set(j, parent);
-      if i_id != j_id {
-        parent[j_id] = i_id;
-      }
-    }
-
-    for i in 0..pixels.len() {
-      for j in (i + 1)..pixels.len() {
-        let dx = (pixels[i].x as i64) - (pixels[j].x as i64);
-        let dy = (pixels[i].y as i64) - (pixels[j].y as i64);
-        if dx * dx + dy * dy <= max_dist_sq {
-          unite_sets(i, j, &mut parent);
-        }
-      }
-    }
-
-    let mut groups: HashMap<usize, Vec<Pixel>> = HashMap::new();
-    for (i, pixel) in pixels.into_iter().enumerate() {
-      let root = find_set(i, &mut parent);
-      groups.entry(root).or_default().push(pixel);
-    }
+    let groups = group_pixels(pixels, self.max_grouping_distance, &self.cancellation)?;
+    let mut features = self.search_limit.matches();
 
-    let features = groups
-      .into_values()
-      .map(|mut group| {
+    for mut group in groups {
+      let limit_reached = features.add(|| {
         let min_x = group.iter().map(|p| p.x).min().unwrap();
         let min_y = group.iter().map(|p| p.y).min().unwrap();
 
@@ -344,17 +1137,28 @@ impl Task for AsyncGetFeaturesFromColor {
           pixel.y -= min_y;
         }
 
-        let feature = Feature { pixels: group };
+        let feature = Feature {
+          pixels: group,
+          anchor: None,
+        };
         FeatureMatch { feature, x: min_x, y: min_y }
-      })
-      .collect();
+      });
 
-    Ok(features)
+      if limit_reached {
+        break;
+      }
+    }
+
+    Ok(features.into_result())
   }
 
   fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
     Ok(output)
   }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue, Error> {
+    self.cancellation.reject(&env, err)
+  }
 }
 
 pub struct AsyncFindFeatures {
@@ -364,6 +1168,10 @@ pub struct AsyncFindFeatures {
   width: u32,
   height: u32,
   rgba_image: RgbaImage,
+  search_limit: SearchLimit,
+  subpixel: bool,
+  orientations: Vec<FeatureOrientation>,
+  cancellation: Cancellation,
 }
 
 impl AsyncFindFeatures {
@@ -371,45 +1179,164 @@ impl AsyncFindFeatures {
     feature: Feature,
     color_tolerance_percent: f64,
     max_mismatch_percent: f64,
-    width: u32,
-    height: u32,
     rgba_image: RgbaImage,
-  ) -> Self {
-    Self {
+    options: Option<FeatureSearchOptions>,
+    cancellation: Cancellation,
+  ) -> Result<Self, Error> {
+    Ok(Self {
       feature,
       color_tolerance_percent,
       max_mismatch_percent,
-      width,
-      height,
+      width: rgba_image.width(),
+      height: rgba_image.height(),
       rgba_image,
+      search_limit: options.into(),
+      subpixel: options
+        .and_then(|options| options.subpixel)
+        .unwrap_or(false),
+      orientations: FeatureOrientation::from_options(options)?,
+      cancellation,
+    })
+  }
+
+  /// Mean colour distance between the feature and the image with the feature's
+  /// top-left placed at the given offset, or `None` if it does not fit.
+  fn mean_distance_at(
+    &self,
+    feature: &Feature,
+    start_x: i64,
+    start_y: i64,
+    min_feat_x: u32,
+    min_feat_y: u32,
+  ) -> Option<f64> {
+    if start_x < 0 || start_y < 0 {
+      return None;
     }
+
+    let mut total_distance = 0.0;
+    for feature_pixel in &feature.pixels {
+      let image_x = start_x as u32 + (feature_pixel.x - min_feat_x);
+      let image_y = start_y as u32 + (feature_pixel.y - min_feat_y);
+      let img_pixel_rgba = self.rgba_image.get_pixel_checked(image_x, image_y)?;
+
+      total_distance += color_distance(
+        feature_pixel.rgba,
+        rgba_into_rgba_number(img_pixel_rgba),
+        true,
+      );
+    }
+
+    Some(total_distance / feature.pixels.len() as f64)
   }
-}
 
-#[napi]
-impl Task for AsyncFindFeatures {
-  type Output = Vec<Pixel>;
-  type JsValue = Vec<Pixel>;
+  /// Refines an integer match by fitting a parabola through the mean colour
+  /// distance at the match and its direct neighbours on each axis. Returns
+  /// `None` unless the match is a local minimum of the distance, so that only
+  /// the best of a cluster of neighbouring matches is reported. On a plateau,
+  /// the top-left position wins.
+  fn refine_subpixel(
+    &self,
+    feature: &Feature,
+    start_x: u32,
+    start_y: u32,
+    min_feat_x: u32,
+    min_feat_y: u32,
+  ) -> Option<(f64, f64)> {
+    let x = start_x as i64;
+    let y = start_y as i64;
+    let distance_at = |x: i64, y: i64| self.mean_distance_at(feature, x, y, min_feat_x, min_feat_y);
+
+    let centre = distance_at(x, y)?;
+    let (left, right) = (distance_at(x - 1, y), distance_at(x + 1, y));
+    let (above, below) = (distance_at(x, y - 1), distance_at(x, y + 1));
+
+    let is_minimum = left.is_none_or(|left| centre < left)
+      && above.is_none_or(|above| centre < above)
+      && right.is_none_or(|right| centre <= right)
+      && below.is_none_or(|below| centre <= below);
+    if !is_minimum {
+      return None;
+    }
 
-  fn compute(&mut self) -> Result<Self::Output, Error> {
-    let mut found_top_lefts = Vec::new();
+    let offset_x = match (left, right) {
+      (Some(left), Some(right)) => quadratic_peak_offset(left, centre, right),
+      _ => 0.0,
+    };
+    let offset_y = match (above, below) {
+      (Some(above), Some(below)) => quadratic_peak_offset(above, centre, below),
+      _ => 0.0,
+    };
+
+    Some((start_x as f64 + offset_x, start_y as f64 + offset_y))
+  }
+
+  fn find_locations(&self) -> Result<SearchMatches<FeatureLocation>, Error> {
+    let mut found_locations = self.search_limit.matches();
+    let mut found_anchors = HashSet::new();
 
     if self.feature.pixels.is_empty() {
-      return Ok(found_top_lefts);
+      return Ok(found_locations);
     }
 
-    let min_feat_x = self.feature.pixels.iter().map(|p| p.x).min().unwrap_or(0);
-    let min_feat_y = self.feature.pixels.iter().map(|p| p.y).min().unwrap_or(0);
-    let max_feat_x = self.feature.pixels.iter().map(|p| p.x).max().unwrap_or(0);
-    let max_feat_y = self.feature.pixels.iter().map(|p| p.y).max().unwrap_or(0);
+    for orientation in &self.orientations {
+      let oriented_feature;
+      let feature = if orientation.is_identity() {
+        &self.feature
+      } else {
+        oriented_feature = orientation.apply(&self.feature);
+        &oriented_feature
+      };
+
+      // Nearest-neighbour resampling can lose every pixel of a thin feature,
+      // which would otherwise match everywhere.
+      if feature.pixels.is_empty() {
+        continue;
+      }
+
+      let limit_reached = self.search_orientation(
+        feature,
+        orientation,
+        &mut found_locations,
+        &mut found_anchors,
+      )?;
+
+      if limit_reached {
+        break;
+      }
+    }
+
+    Ok(found_locations)
+  }
+
+  /// Searches the image for a single orientation of the feature, skipping
+  /// positions whose anchor was already matched by an earlier orientation.
+  /// Rotated features have differently sized bounding boxes, so their top-left
+  /// corners can't be compared. Returns whether the search limit has been
+  /// reached.
+  fn search_orientation(
+    &self,
+    feature: &Feature,
+    orientation: &FeatureOrientation,
+    found_locations: &mut SearchMatches<FeatureLocation>,
+    found_anchors: &mut HashSet<(i64, i64)>,
+  ) -> Result<bool, Error> {
+    let min_feat_x = feature.pixels.iter().map(|p| p.x).min().unwrap_or(0);
+    let min_feat_y = feature.pixels.iter().map(|p| p.y).min().unwrap_or(0);
+    let max_feat_x = feature.pixels.iter().map(|p| p.x).max().unwrap_or(0);
+    let max_feat_y = feature.pixels.iter().map(|p| p.y).max().unwrap_or(0);
 
     let feature_width = max_feat_x - min_feat_x + 1;
     let feature_height = max_feat_y - min_feat_y + 1;
 
     if feature_width > self.width || feature_height > self.height {
-      return Ok(found_top_lefts);
+      return Ok(false);
     }
 
+    let anchor = feature.anchor.unwrap_or(FeatureAnchor {
+      x: i64::from(min_feat_x + feature_width / 2),
+      y: i64::from(min_feat_y + feature_height / 2),
+    });
+
     let max_color_distance: f64 = if true {
       510.0 // sqrt(255*255 * 4)
     } else {
@@ -417,16 +1344,24 @@ impl Task for AsyncFindFeatures {
     };
     let actual_color_tolerance_value = max_color_distance * self.color_tolerance_percent;
 
-    let total_feature_pixels = self.feature.pixels.len() as f64;
+    let total_feature_pixels = feature.pixels.len() as f64;
     let max_mismatches_count = (total_feature_pixels * self.max_mismatch_percent).round() as u32;
 
     let use_alpha_for_comparison = true;
 
     for start_y in 0..=(self.height - feature_height) {
+      self.cancellation.check()?;
+
       for start_x in 0..=(self.width - feature_width) {
+        let anchor_x = i64::from(start_x) + anchor.x - i64::from(min_feat_x);
+        let anchor_y = i64::from(start_y) + anchor.y - i64::from(min_feat_y);
+        if found_anchors.contains(&(anchor_x, anchor_y)) {
+          continue;
+        }
+
         let mut current_mismatches = 0;
 
-        for feature_pixel in &self.feature.pixels {
+        for feature_pixel in &feature.pixels {
           let current_image_x = start_x + (feature_pixel.x - min_feat_x);
           let current_image_y = start_y + (feature_pixel.y - min_feat_y);
 
@@ -460,24 +1395,304 @@ impl Task for AsyncFindFeatures {
         }
 
         if current_mismatches <= max_mismatches_count {
-          let top_left_pixel_rgba_raw = self.rgba_image.get_pixel(start_x, start_y);
-          let top_left_rgba_u32 = rgba_into_rgba_number(top_left_pixel_rgba_raw);
+          let subpixel_position = if self.subpixel {
+            match self.refine_subpixel(feature, start_x, start_y, min_feat_x, min_feat_y) {
+              Some(position) => Some(position),
+              None => continue,
+            }
+          } else {
+            None
+          };
 
-          found_top_lefts.push(Pixel {
+          found_anchors.insert((anchor_x, anchor_y));
+          let limit_reached = found_locations.add(|| FeatureLocation {
             x: start_x,
             y: start_y,
-            rgba: top_left_rgba_u32,
+            width: feature_width,
+            height: feature_height,
+            anchor_x,
+            anchor_y,
+            subpixel_x: subpixel_position.map(|(x, _)| x),
+            subpixel_y: subpixel_position.map(|(_, y)| y),
+            angle: orientation.angle,
+            reflected: orientation.reflected,
+          });
+
+          if limit_reached {
+            return Ok(true);
+          }
+        }
+      }
+    }
+
+    Ok(false)
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct FeatureOrientation {
+  angle: f64,
+  reflected: bool,
+}
+
+impl FeatureOrientation {
+  const DEFAULT_ANGLE_STEP: f64 = 15.0;
+  const MAX_ANGLE_COUNT: f64 = 3600.0;
+
+  /// Orientations to search, ordered so that the least transformed ones are
+  /// tried first and win when several orientations match the same position.
+  fn from_options(options: Option<FeatureSearchOptions>) -> Result<Vec<Self>, Error> {
+    let options = options.unwrap_or_default();
+    let (first, second) = (
+      options.min_angle.unwrap_or(0.0),
+      options.max_angle.unwrap_or(0.0),
+    );
+    let (min_angle, max_angle) = (first.min(second), first.max(second));
+    let angle_step = options.angle_step.unwrap_or(Self::DEFAULT_ANGLE_STEP);
+
+    if !min_angle.is_finite() || !max_angle.is_finite() {
+      return Err(Error::from_reason("Angles must be finite numbers"));
+    }
+    if !angle_step.is_finite() || angle_step <= 0.0 {
+      return Err(Error::from_reason(
+        "The angle step must be a positive number",
+      ));
+    }
+
+    // Allow for rounding error so that the maximum itself is included.
+    let step_count = ((max_angle - min_angle) / angle_step + 1e-9).floor();
+    if step_count >= Self::MAX_ANGLE_COUNT {
+      return Err(Error::from_reason(format!(
+        "The angle range and step give more than {} angles to search",
+        Self::MAX_ANGLE_COUNT
+      )));
+    }
+    let step_count = step_count as u32;
+    let mut angles: Vec<f64> = (0..=step_count)
+      .map(|step| min_angle + f64::from(step) * angle_step)
+      .collect();
+    angles.sort_by(|a, b| a.abs().total_cmp(&b.abs()));
+
+    let reflections: &[bool] = if options.include_reflections.unwrap_or(false) {
+      &[false, true]
+    } else {
+      &[false]
+    };
+
+    Ok(
+      reflections
+        .iter()
+        .flat_map(|&reflected| angles.iter().map(move |&angle| Self { angle, reflected }))
+        .collect(),
+    )
+  }
+
+  fn is_identity(&self) -> bool {
+    !self.reflected && self.angle.rem_euclid(360.0) == 0.0
+  }
+
+  /// Maps a point, relative to the centre of the feature, into this orientation.
+  fn transform(&self, x: f64, y: f64) -> (f64, f64) {
+    let x = if self.reflected { -x } else { x };
+    let (sin, cos) = self.angle.to_radians().sin_cos();
+    (x * cos - y * sin, x * sin + y * cos)
+  }
+
+  fn inverse_transform(&self, x: f64, y: f64) -> (f64, f64) {
+    let (sin, cos) = self.angle.to_radians().sin_cos();
+    let (x, y) = (x * cos + y * sin, -x * sin + y * cos);
+    (if self.reflected { -x } else { x }, y)
+  }
+
+  /// The number of clockwise quarter turns, if the angle is a right angle.
+  fn quarter_turns(&self) -> Option<u8> {
+    let angle = self.angle.rem_euclid(360.0);
+    (angle % 90.0 == 0.0).then(|| (angle / 90.0) as u8 % 4)
+  }
+
+  /// Resamples the feature into this orientation. Right angles swap and flip
+  /// pixel indices exactly; other angles use nearest-neighbour lookups from
+  /// pixel centres, so rotated features have no gaps between their pixels.
+  fn apply(&self, feature: &Feature) -> Feature {
+    let min_x = feature.pixels.iter().map(|p| p.x).min().unwrap_or(0);
+    let min_y = feature.pixels.iter().map(|p| p.y).min().unwrap_or(0);
+    let max_x = feature.pixels.iter().map(|p| p.x).max().unwrap_or(0);
+    let max_y = feature.pixels.iter().map(|p| p.y).max().unwrap_or(0);
+
+    match self.quarter_turns() {
+      Some(turns) => self.apply_quarter_turns(feature, turns, min_x, min_y, max_x, max_y),
+      None => self.apply_resampled(feature, min_x, min_y, max_x, max_y),
+    }
+  }
+
+  fn apply_quarter_turns(
+    &self,
+    feature: &Feature,
+    turns: u8,
+    min_x: u32,
+    min_y: u32,
+    max_x: u32,
+    max_y: u32,
+  ) -> Feature {
+    let width = i64::from(max_x - min_x);
+    let height = i64::from(max_y - min_y);
+    // Maps a position relative to the top-left of the bounding box, where
+    // `width` and `height` are the largest indices.
+    let map = |x: i64, y: i64| {
+      let x = if self.reflected { width - x } else { x };
+      match turns {
+        0 => (x, y),
+        1 => (height - y, x),
+        2 => (width - x, height - y),
+        _ => (y, width - x),
+      }
+    };
+
+    let pixels = feature
+      .pixels
+      .iter()
+      .map(|p| {
+        let (x, y) = map(i64::from(p.x - min_x), i64::from(p.y - min_y));
+        Pixel {
+          x: x as u32,
+          y: y as u32,
+          rgba: p.rgba,
+        }
+      })
+      .collect();
+
+    let anchor = feature.anchor.map(|anchor| {
+      let (x, y) = map(anchor.x - i64::from(min_x), anchor.y - i64::from(min_y));
+      FeatureAnchor { x, y }
+    });
+
+    Feature { pixels, anchor }
+  }
+
+  fn apply_resampled(
+    &self,
+    feature: &Feature,
+    min_x: u32,
+    min_y: u32,
+    max_x: u32,
+    max_y: u32,
+  ) -> Feature {
+    // Pixel `x` covers `x..x + 1`, so the bounding box runs from `min_x` to
+    // `max_x + 1` and pixel centres sit at `x + 0.5`.
+    let centre_x = f64::from(min_x + max_x + 1) / 2.0;
+    let centre_y = f64::from(min_y + max_y + 1) / 2.0;
+
+    let source: HashMap<(i64, i64), u32> = feature
+      .pixels
+      .iter()
+      .map(|p| ((p.x as i64, p.y as i64), p.rgba))
+      .collect();
+
+    let corners = [
+      (f64::from(min_x), f64::from(min_y)),
+      (f64::from(max_x + 1), f64::from(min_y)),
+      (f64::from(min_x), f64::from(max_y + 1)),
+      (f64::from(max_x + 1), f64::from(max_y + 1)),
+    ]
+    .map(|(x, y)| self.transform(x - centre_x, y - centre_y));
+    let left = corners
+      .iter()
+      .map(|c| c.0)
+      .fold(f64::INFINITY, f64::min)
+      .floor() as i64;
+    let top = corners
+      .iter()
+      .map(|c| c.1)
+      .fold(f64::INFINITY, f64::min)
+      .floor() as i64;
+    let right = corners
+      .iter()
+      .map(|c| c.0)
+      .fold(f64::NEG_INFINITY, f64::max)
+      .ceil() as i64;
+    let bottom = corners
+      .iter()
+      .map(|c| c.1)
+      .fold(f64::NEG_INFINITY, f64::max)
+      .ceil() as i64;
+
+    let mut pixels = Vec::new();
+    for y in top..bottom {
+      for x in left..right {
+        let (source_x, source_y) = self.inverse_transform(x as f64 + 0.5, y as f64 + 0.5);
+        let source_position = (
+          (source_x + centre_x).floor() as i64,
+          (source_y + centre_y).floor() as i64,
+        );
+
+        if let Some(&rgba) = source.get(&source_position) {
+          pixels.push(Pixel {
+            x: (x - left) as u32,
+            y: (y - top) as u32,
+            rgba,
           });
         }
       }
     }
 
-    Ok(found_top_lefts)
+    let anchor = feature.anchor.map(|anchor| {
+      let (x, y) = self.transform(
+        anchor.x as f64 + 0.5 - centre_x,
+        anchor.y as f64 + 0.5 - centre_y,
+      );
+      FeatureAnchor {
+        x: x.floor() as i64 - left,
+        y: y.floor() as i64 - top,
+      }
+    });
+
+    Feature { pixels, anchor }
+  }
+}
+
+#[napi]
+impl Task for AsyncFindFeatures {
+  type Output = SearchResult<FeatureLocation>;
+  type JsValue = SearchResult<FeatureLocation>;
+
+  fn compute(&mut self) -> Result<Self::Output, Error> {
+    Ok(self.find_locations()?.into_result())
   }
 
   fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
     Ok(output)
   }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue, Error> {
+    self.cancellation.reject(&env, err)
+  }
+}
+
+/// Reports the top-left pixel of each match, as `findFeature` always has.
+pub struct AsyncFindFeatureTopLefts(AsyncFindFeatures);
+
+#[napi]
+impl Task for AsyncFindFeatureTopLefts {
+  type Output = SearchResult<Pixel>;
+  type JsValue = SearchResult<Pixel>;
+
+  fn compute(&mut self) -> Result<Self::Output, Error> {
+    let search = &self.0;
+    let top_lefts = search.find_locations()?.map(|location| Pixel {
+      x: location.x,
+      y: location.y,
+      rgba: rgba_into_rgba_number(search.rgba_image.get_pixel(location.x, location.y)),
+    });
+    Ok(top_lefts.into_result())
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
+    Ok(output)
+  }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue, Error> {
+    self.0.cancellation.reject(&env, err)
+  }
 }
 
 pub struct AsyncCheckFeature {
@@ -634,6 +1849,7 @@ impl Task for AsyncGetFeature {
 
     Ok(Feature {
       pixels: pixels_in_region,
+      anchor: None,
     })
   }
 
@@ -648,16 +1864,25 @@ pub struct AsyncGetColourFrequencies {
   end_x: u32,
   end_y: u32,
   rgba_image: RgbaImage,
+  cancellation: Cancellation,
 }
 
 impl AsyncGetColourFrequencies {
-  pub fn new(start_x: u32, start_y: u32, end_x: u32, end_y: u32, rgba_image: RgbaImage) -> Self {
+  pub fn new(
+    start_x: u32,
+    start_y: u32,
+    end_x: u32,
+    end_y: u32,
+    rgba_image: RgbaImage,
+    cancellation: Cancellation,
+  ) -> Self {
     Self {
       start_x,
       start_y,
       end_x,
       end_y,
       rgba_image,
+      cancellation,
     }
   }
 }
@@ -685,6 +1910,8 @@ impl Task for AsyncGetColourFrequencies {
     let mut colour_counts: HashMap<u32, u32> = HashMap::new();
 
     for y in min_y..=max_y {
+      self.cancellation.check()?;
+
       for x in min_x..=max_x {
         let rgba_raw = self.rgba_image.get_pixel(x, y);
         let rgba_u32 = rgba_into_rgba_number(rgba_raw);
@@ -703,4 +1930,8 @@ impl Task for AsyncGetColourFrequencies {
   fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
     Ok(output)
   }
-}
\ No newline at end of file
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue, Error> {
+    self.cancellation.reject(&env, err)
+  }
+}
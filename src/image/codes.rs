@@ -0,0 +1,327 @@
+use image::{imageops, GrayImage, RgbaImage};
+use napi::{Env, Error, Task};
+use rxing::{BarcodeFormat, DecodeHints, Exceptions};
+use std::collections::HashSet;
+
+use crate::image::{Point, Region};
+
+#[napi(string_enum)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CodeFormat {
+  QrCode,
+  DataMatrix,
+  Ean8,
+  Ean13,
+  UpcA,
+  UpcE,
+  Code128,
+}
+
+impl CodeFormat {
+  const ALL: [CodeFormat; 7] = [
+    CodeFormat::QrCode,
+    CodeFormat::DataMatrix,
+    CodeFormat::Ean8,
+    CodeFormat::Ean13,
+    CodeFormat::UpcA,
+    CodeFormat::UpcE,
+    CodeFormat::Code128,
+  ];
+
+  fn barcode_format(self) -> BarcodeFormat {
+    match self {
+      CodeFormat::QrCode => BarcodeFormat::QR_CODE,
+      CodeFormat::DataMatrix => BarcodeFormat::DATA_MATRIX,
+      CodeFormat::Ean8 => BarcodeFormat::EAN_8,
+      CodeFormat::Ean13 => BarcodeFormat::EAN_13,
+      CodeFormat::UpcA => BarcodeFormat::UPC_A,
+      CodeFormat::UpcE => BarcodeFormat::UPC_E,
+      CodeFormat::Code128 => BarcodeFormat::CODE_128,
+    }
+  }
+
+  fn from_barcode_format(format: &BarcodeFormat) -> Option<Self> {
+    CodeFormat::ALL
+      .into_iter()
+      .find(|code_format| code_format.barcode_format() == *format)
+  }
+}
+
+#[napi(object)]
+pub struct DecodedCode {
+  pub format: CodeFormat,
+  pub text: String,
+  /// The code's corners in image coordinates, clockwise from its top-left
+  /// as read.
+  pub polygon: Vec<Point>,
+  /// Set when the code's edges couldn't be measured, so `polygon` only joins
+  /// the points the detector reported: the finder pattern centres of a QR
+  /// code, or two points on the scanned row of a 1D code.
+  pub approximate_polygon: bool,
+}
+
+/// The fraction of a 1D code's scanned row that neighbouring rows must repeat
+/// to count as part of its bars.
+const MIN_ROW_MATCH: f64 = 0.9;
+
+/// The searched region split into dark and light, for measuring codes around
+/// the points the detector reports.
+struct Modules {
+  luma: GrayImage,
+  threshold: u8,
+}
+
+impl Modules {
+  fn new(luma: GrayImage) -> Self {
+    let (min, max) = luma.pixels().fold((u8::MAX, u8::MIN), |(min, max), pixel| {
+      (min.min(pixel[0]), max.max(pixel[0]))
+    });
+    let threshold = ((u16::from(min) + u16::from(max)) / 2) as u8;
+    Self { luma, threshold }
+  }
+
+  fn is_dark(&self, (x, y): (f64, f64)) -> Option<bool> {
+    if x < 0.0 || y < 0.0 {
+      return None;
+    }
+    let pixel = self.luma.get_pixel_checked(x as u32, y as u32)?;
+    Some(pixel[0] <= self.threshold)
+  }
+
+  /// The code's corners, or `None` if its edges couldn't be measured.
+  fn polygon(&self, format: CodeFormat, points: &[(f64, f64)]) -> Option<Vec<(f64, f64)>> {
+    match format {
+      CodeFormat::QrCode => self.qr_code_polygon(points),
+      // The Data Matrix detector already reports the corners in order.
+      CodeFormat::DataMatrix => Some(points.to_vec()),
+      _ => self.linear_polygon(points),
+    }
+  }
+
+  /// QR codes come either as their four corners, top-left, top-right,
+  /// bottom-left then bottom-right, or as the centres of the bottom-left,
+  /// top-left and top-right finder patterns, depending on which detector
+  /// found them. The finder pattern at the top-left corner forms the right
+  /// angle either way.
+  fn qr_code_polygon(&self, points: &[(f64, f64)]) -> Option<Vec<(f64, f64)>> {
+    let &[p0, p1, p2, ..] = points else {
+      return None;
+    };
+    let corner_cos = |corner: (f64, f64), a: (f64, f64), b: (f64, f64)| {
+      let (a, b) = (sub(a, corner), sub(b, corner));
+      (a.0 * b.0 + a.1 * b.1).abs() / (length(a) * length(b))
+    };
+
+    if corner_cos(p0, p1, p2) < corner_cos(p1, p0, p2) {
+      let p3 = *points.get(3)?;
+      return Some(vec![p0, p1, p3, p2]);
+    }
+
+    let (top_left, top_right, bottom_left) = (p1, p2, p0);
+    let across = unit(sub(top_right, top_left))?;
+    let down = unit(sub(bottom_left, top_left))?;
+    let extent = (self.finder_extent(top_left, scale(across, -1.0))?
+      + self.finder_extent(top_left, scale(down, -1.0))?)
+      / 2.0;
+
+    let top_left = sub(top_left, scale(add(across, down), extent));
+    let top_right = add(top_right, scale(sub(across, down), extent));
+    let bottom_left = add(bottom_left, scale(sub(down, across), extent));
+    let bottom_right = sub(add(top_right, bottom_left), top_left);
+    Some(vec![top_left, top_right, bottom_right, bottom_left])
+  }
+
+  /// The distance from a finder pattern's centre to its outer edge, found by
+  /// crossing its dark centre, light ring and dark ring.
+  fn finder_extent(&self, centre: (f64, f64), direction: (f64, f64)) -> Option<f64> {
+    let mut dark = self.is_dark(centre)?;
+    if !dark {
+      return None;
+    }
+
+    let mut transitions = 0;
+    let mut distance = 0.0;
+    while transitions < 3 {
+      distance += 0.5;
+      let is_dark = self.is_dark(add(centre, scale(direction, distance)))?;
+      if is_dark != dark {
+        dark = is_dark;
+        transitions += 1;
+      }
+    }
+    Some(distance)
+  }
+
+  /// 1D codes come as two points inside their end guards on the scanned row.
+  /// The polygon covers every row that repeats its bars, out to the last bar
+  /// before the quiet zones.
+  fn linear_polygon(&self, points: &[(f64, f64)]) -> Option<Vec<(f64, f64)>> {
+    let &[start, end] = points else {
+      return None;
+    };
+    let row_length = length(sub(end, start));
+    let along = unit(sub(end, start))?;
+    let across = (-along.1, along.0);
+
+    let row = |offset: f64| -> Option<Vec<bool>> {
+      (0..=row_length.round() as u32)
+        .map(|step| {
+          let position = add(start, scale(along, f64::from(step)));
+          self.is_dark(add(position, scale(across, offset)))
+        })
+        .collect()
+    };
+    let scanned_row = row(0.0)?;
+    let repeats_bars = |offset: f64| {
+      row(offset).is_some_and(|row| {
+        let matching = row.iter().zip(&scanned_row).filter(|(a, b)| a == b).count();
+        matching as f64 >= scanned_row.len() as f64 * MIN_ROW_MATCH
+      })
+    };
+    let bar_extent = |sign: f64| {
+      (1..)
+        .map(f64::from)
+        .take_while(|&offset| repeats_bars(sign * offset))
+        .last()
+        .unwrap_or(0.0)
+    };
+    let above = bar_extent(-1.0);
+    let below = bar_extent(1.0);
+
+    // The quiet zones are at least a tenth of the code; gaps inside the end
+    // guards are much narrower.
+    let quiet_zone = (row_length / 20.0).max(2.0);
+    let guard_extent = |from: (f64, f64), direction: (f64, f64)| {
+      let mut last_dark = 0.0;
+      let mut distance = 0.0;
+      while distance - last_dark <= quiet_zone {
+        distance += 1.0;
+        match self.is_dark(add(from, scale(direction, distance))) {
+          Some(true) => last_dark = distance,
+          Some(false) => {}
+          None => break,
+        }
+      }
+      last_dark
+    };
+    let start = sub(start, scale(along, guard_extent(start, scale(along, -1.0))));
+    let end = add(end, scale(along, guard_extent(end, along)));
+
+    Some(vec![
+      sub(start, scale(across, above)),
+      sub(end, scale(across, above)),
+      add(end, scale(across, below)),
+      add(start, scale(across, below)),
+    ])
+  }
+}
+
+fn add(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+  (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+  (a.0 - b.0, a.1 - b.1)
+}
+
+fn scale(a: (f64, f64), factor: f64) -> (f64, f64) {
+  (a.0 * factor, a.1 * factor)
+}
+
+fn length(a: (f64, f64)) -> f64 {
+  a.0.hypot(a.1)
+}
+
+fn unit(a: (f64, f64)) -> Option<(f64, f64)> {
+  let length = length(a);
+  (length > 0.0).then(|| scale(a, 1.0 / length))
+}
+
+pub struct AsyncDecodeCodes {
+  rgba_image: RgbaImage,
+  region: Option<Region>,
+}
+
+impl AsyncDecodeCodes {
+  pub fn new(rgba_image: RgbaImage, region: Option<Region>) -> Self {
+    Self { rgba_image, region }
+  }
+}
+
+#[napi]
+impl Task for AsyncDecodeCodes {
+  type Output = Vec<DecodedCode>;
+  type JsValue = Vec<DecodedCode>;
+
+  fn compute(&mut self) -> Result<Self::Output, Error> {
+    let Region {
+      x,
+      y,
+      width,
+      height,
+    } = Region::within(self.region, &self.rgba_image);
+
+    if width == 0 || height == 0 {
+      return Ok(Vec::new());
+    }
+
+    let luma = imageops::grayscale(&*imageops::crop_imm(&self.rgba_image, x, y, width, height));
+
+    let mut hints = DecodeHints {
+      PossibleFormats: Some(
+        CodeFormat::ALL
+          .into_iter()
+          .map(CodeFormat::barcode_format)
+          .collect::<HashSet<_>>(),
+      ),
+      ..Default::default()
+    };
+
+    let results = match rxing::helpers::detect_multiple_in_luma_with_hints(
+      luma.as_raw().clone(),
+      width,
+      height,
+      &mut hints,
+    ) {
+      Ok(results) => results,
+      Err(Exceptions::NotFoundException(_)) => Vec::new(),
+      Err(error) => return Err(Error::from_reason(error.to_string())),
+    };
+
+    let modules = Modules::new(luma);
+    let codes = results
+      .into_iter()
+      .filter_map(|result| {
+        let format = CodeFormat::from_barcode_format(result.getBarcodeFormat())?;
+        let points: Vec<(f64, f64)> = result
+          .getPoints()
+          .iter()
+          .map(|point| (f64::from(point.x), f64::from(point.y)))
+          .collect();
+        let measured_polygon = modules.polygon(format, &points);
+        let approximate_polygon = measured_polygon.is_none();
+        let polygon = measured_polygon
+          .unwrap_or(points)
+          .into_iter()
+          .map(|(point_x, point_y)| Point {
+            x: point_x + f64::from(x),
+            y: point_y + f64::from(y),
+          })
+          .collect();
+
+        Some(DecodedCode {
+          format,
+          text: result.getText().to_string(),
+          polygon,
+          approximate_polygon,
+        })
+      })
+      .collect();
+
+    Ok(codes)
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
+    Ok(output)
+  }
+}
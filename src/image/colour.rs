@@ -0,0 +1,295 @@
+use image::Rgba;
+use napi::{bindgen_prelude::Either, Error};
+
+use crate::image::{rgba_into_rgba_number, rgba_number_into_rgba};
+
+/// A colour given either as a packed `0xRRGGBBAA` number or as a CSS-style
+/// string accepted by `parseColour`.
+pub type Colour = Either<u32, String>;
+
+#[napi(object)]
+#[derive(Clone, Copy, Debug)]
+pub struct RgbaComponents {
+  pub red: u32,
+  pub green: u32,
+  pub blue: u32,
+  pub alpha: u32,
+}
+
+#[napi(object)]
+#[derive(Clone, Copy, Debug)]
+pub struct Hsv {
+  /// Degrees, from 0 up to 360.
+  pub hue: f64,
+  /// From 0 to 1.
+  pub saturation: f64,
+  /// From 0 to 1.
+  pub value: f64,
+  /// From 0 to 1.
+  pub alpha: f64,
+}
+
+/// CIE L*a*b* under the D65 illuminant.
+#[napi(object)]
+#[derive(Clone, Copy, Debug)]
+pub struct Lab {
+  pub l: f64,
+  pub a: f64,
+  pub b: f64,
+  /// From 0 to 1.
+  pub alpha: f64,
+}
+
+#[derive(Debug)]
+pub enum ColourParseError {
+  InvalidHex(String),
+  InvalidFunction(String),
+  InvalidComponent(String),
+  UnknownFormat(String),
+}
+
+impl From<ColourParseError> for Error {
+  fn from(value: ColourParseError) -> Error {
+    Error::from_reason(format!("{:?}", value))
+  }
+}
+
+pub fn resolve_colour(colour: Colour) -> Result<u32, Error> {
+  match colour {
+    Either::A(rgba_number) => Ok(rgba_number),
+    Either::B(text) => Ok(parse_colour_text(&text)?),
+  }
+}
+
+pub fn resolve_optional_colour(colour: Option<Colour>) -> Result<Option<u32>, Error> {
+  colour.map(resolve_colour).transpose()
+}
+
+fn parse_hex(hex: &str) -> Result<u32, ColourParseError> {
+  let invalid = || ColourParseError::InvalidHex(hex.to_string());
+  if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+    return Err(invalid());
+  }
+
+  // Short forms repeat each digit, so `#f80` is `#ff8800`.
+  let expanded: String = match hex.len() {
+    3 | 4 => hex.chars().flat_map(|c| [c, c]).collect(),
+    6 | 8 => hex.to_string(),
+    _ => return Err(invalid()),
+  };
+  let value = u32::from_str_radix(&expanded, 16).map_err(|_| invalid())?;
+
+  Ok(if expanded.len() == 6 {
+    (value << 8) | 0xFF
+  } else {
+    value
+  })
+}
+
+/// Parses a number that may be followed by `%`, returning the value and whether
+/// it was a percentage.
+fn parse_component(component: &str) -> Result<(f64, bool), ColourParseError> {
+  let invalid = || ColourParseError::InvalidComponent(component.to_string());
+  let (number, is_percentage) = match component.strip_suffix('%') {
+    Some(number) => (number, true),
+    None => (component.strip_suffix("deg").unwrap_or(component), false),
+  };
+  let value: f64 = number.parse().map_err(|_| invalid())?;
+  if !value.is_finite() {
+    return Err(invalid());
+  }
+  Ok((value, is_percentage))
+}
+
+fn channel_from_component(component: &str) -> Result<u8, ColourParseError> {
+  let (value, is_percentage) = parse_component(component)?;
+  let value = if is_percentage { value * 2.55 } else { value };
+  Ok(value.round().clamp(0.0, 255.0) as u8)
+}
+
+fn alpha_from_component(component: Option<&str>) -> Result<u8, ColourParseError> {
+  let Some(component) = component else {
+    return Ok(255);
+  };
+  let (value, is_percentage) = parse_component(component)?;
+  let fraction = if is_percentage { value / 100.0 } else { value };
+  Ok((fraction.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+/// Reads a percentage as a fraction. As in CSS, `hsl()` saturation and
+/// lightness must be written with `%`.
+fn fraction_from_percentage(component: &str) -> Result<f64, ColourParseError> {
+  let (value, is_percentage) = parse_component(component)?;
+  if !is_percentage {
+    return Err(ColourParseError::InvalidComponent(component.to_string()));
+  }
+  Ok((value / 100.0).clamp(0.0, 1.0))
+}
+
+fn hue_to_rgb(hue: f64, chroma: f64, lightness_offset: f64) -> [f64; 3] {
+  let sector = hue.rem_euclid(360.0) / 60.0;
+  let second = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+  let [r, g, b] = match sector as u32 {
+    0 => [chroma, second, 0.0],
+    1 => [second, chroma, 0.0],
+    2 => [0.0, chroma, second],
+    3 => [0.0, second, chroma],
+    4 => [second, 0.0, chroma],
+    _ => [chroma, 0.0, second],
+  };
+  [
+    r + lightness_offset,
+    g + lightness_offset,
+    b + lightness_offset,
+  ]
+}
+
+/// Parses `rgb()`, `rgba()`, `hsl()` and `hsla()` with comma or space
+/// separated arguments and an optional `/ alpha`.
+fn parse_function(text: &str) -> Result<u32, ColourParseError> {
+  let invalid = || ColourParseError::InvalidFunction(text.to_string());
+  let (name, arguments) = text
+    .strip_suffix(')')
+    .and_then(|text| text.split_once('('))
+    .ok_or_else(invalid)?;
+  let arguments: Vec<&str> = arguments
+    .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+    .filter(|argument| !argument.is_empty())
+    .collect();
+  if arguments.len() != 3 && arguments.len() != 4 {
+    return Err(invalid());
+  }
+  let alpha = alpha_from_component(arguments.get(3).copied())?;
+
+  let [red, green, blue] = match name.trim() {
+    "rgb" | "rgba" => [
+      channel_from_component(arguments[0])?,
+      channel_from_component(arguments[1])?,
+      channel_from_component(arguments[2])?,
+    ],
+    "hsl" | "hsla" => {
+      let (hue, _) = parse_component(arguments[0])?;
+      let saturation = fraction_from_percentage(arguments[1])?;
+      let lightness = fraction_from_percentage(arguments[2])?;
+      let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+      hue_to_rgb(hue, chroma, lightness - chroma / 2.0)
+        .map(|channel| (channel * 255.0).round().clamp(0.0, 255.0) as u8)
+    }
+    _ => return Err(invalid()),
+  };
+
+  Ok(rgba_into_rgba_number(&Rgba([red, green, blue, alpha])))
+}
+
+pub fn parse_colour_text(text: &str) -> Result<u32, ColourParseError> {
+  let text = text.trim().to_ascii_lowercase();
+  if let Some(hex) = text.strip_prefix('#') {
+    parse_hex(hex)
+  } else if text.contains('(') {
+    parse_function(&text)
+  } else {
+    Err(ColourParseError::UnknownFormat(text))
+  }
+}
+
+/// Parses `#RGB`, `#RGBA`, `#RRGGBB`, `#RRGGBBAA`, `rgb()`/`rgba()` and
+/// `hsl()`/`hsla()` into a packed `0xRRGGBBAA` number.
+#[napi]
+pub fn parse_colour(text: String) -> Result<u32, Error> {
+  Ok(parse_colour_text(&text)?)
+}
+
+/// Packs channels from 0 to 255 into a `0xRRGGBBAA` number. Alpha defaults to
+/// opaque.
+#[napi]
+pub fn rgba_from_components(red: u32, green: u32, blue: u32, alpha: Option<u32>) -> u32 {
+  let channel = |value: u32| value.min(255) as u8;
+  rgba_into_rgba_number(&Rgba([
+    channel(red),
+    channel(green),
+    channel(blue),
+    channel(alpha.unwrap_or(255)),
+  ]))
+}
+
+#[napi]
+pub fn rgba_components(rgba: Colour) -> Result<RgbaComponents, Error> {
+  let [red, green, blue, alpha] = rgba_number_into_rgba(resolve_colour(rgba)?)
+    .0
+    .map(u32::from);
+  Ok(RgbaComponents {
+    red,
+    green,
+    blue,
+    alpha,
+  })
+}
+
+/// Formats a colour as `#rrggbbaa`.
+#[napi]
+pub fn rgba_to_hex(rgba: Colour) -> Result<String, Error> {
+  Ok(format!("#{:08x}", resolve_colour(rgba)?))
+}
+
+#[napi]
+pub fn rgba_to_hsv(rgba: Colour) -> Result<Hsv, Error> {
+  let [red, green, blue, alpha] = rgba_number_into_rgba(resolve_colour(rgba)?)
+    .0
+    .map(|channel| f64::from(channel) / 255.0);
+  let max = red.max(green).max(blue);
+  let min = red.min(green).min(blue);
+  let delta = max - min;
+
+  let hue = if delta == 0.0 {
+    0.0
+  } else if max == red {
+    60.0 * ((green - blue) / delta).rem_euclid(6.0)
+  } else if max == green {
+    60.0 * ((blue - red) / delta + 2.0)
+  } else {
+    60.0 * ((red - green) / delta + 4.0)
+  };
+
+  Ok(Hsv {
+    hue,
+    saturation: if max == 0.0 { 0.0 } else { delta / max },
+    value: max,
+    alpha,
+  })
+}
+
+#[napi]
+pub fn rgba_to_lab(rgba: Colour) -> Result<Lab, Error> {
+  let [red, green, blue, alpha] = rgba_number_into_rgba(resolve_colour(rgba)?)
+    .0
+    .map(|channel| f64::from(channel) / 255.0);
+  let linear = |channel: f64| {
+    if channel <= 0.04045 {
+      channel / 12.92
+    } else {
+      ((channel + 0.055) / 1.055).powf(2.4)
+    }
+  };
+  let (red, green, blue) = (linear(red), linear(green), linear(blue));
+
+  // sRGB to XYZ, relative to the D65 white point.
+  let x = (0.4124 * red + 0.3576 * green + 0.1805 * blue) / 0.95047;
+  let y = 0.2126 * red + 0.7152 * green + 0.0722 * blue;
+  let z = (0.0193 * red + 0.1192 * green + 0.9505 * blue) / 1.08883;
+
+  let f = |t: f64| {
+    if t > 216.0 / 24389.0 {
+      t.cbrt()
+    } else {
+      (24389.0 / 27.0 * t + 16.0) / 116.0
+    }
+  };
+  let (fx, fy, fz) = (f(x), f(y), f(z));
+
+  Ok(Lab {
+    l: 116.0 * fy - 16.0,
+    a: 500.0 * (fx - fy),
+    b: 200.0 * (fy - fz),
+    alpha,
+  })
+}
@@ -0,0 +1,119 @@
+use image::{Rgba, RgbaImage};
+
+use crate::image::{rgba_number_into_rgba, Region};
+
+#[napi(string_enum)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+  /// Draws the source over the destination using the source's alpha.
+  #[default]
+  Over,
+  /// Multiplies the colours together, darkening the destination, then draws
+  /// the result over it using the source's alpha.
+  Multiply,
+  /// Overwrites the destination, alpha included.
+  Replace,
+}
+
+#[napi(object)]
+#[derive(Clone, Copy, Default)]
+pub struct CompositeOptions {
+  /// Defaults to `Over`.
+  pub mode: Option<BlendMode>,
+  /// Scales the source's alpha, between 0 and 1. Defaults to 1.
+  pub opacity: Option<f64>,
+}
+
+/// Combines a source colour into a destination pixel. `coverage` scales the
+/// source's contribution, as its alpha does.
+pub fn composite_pixel(
+  destination: &mut Rgba<u8>,
+  source: Rgba<u8>,
+  mode: BlendMode,
+  coverage: f64,
+) {
+  let coverage = coverage.clamp(0.0, 1.0);
+
+  if mode == BlendMode::Replace {
+    for channel in 0..4 {
+      let blended = f64::from(source.0[channel]) * coverage
+        + f64::from(destination.0[channel]) * (1.0 - coverage);
+      destination.0[channel] = blended.round() as u8;
+    }
+    return;
+  }
+
+  // Porter-Duff source-over, with colours weighted by their alpha so that a
+  // transparent destination doesn't tint the result.
+  let alpha = f64::from(source.0[3]) / 255.0 * coverage;
+  let destination_alpha = f64::from(destination.0[3]) / 255.0;
+  let output_alpha = alpha + destination_alpha * (1.0 - alpha);
+
+  for channel in 0..3 {
+    let destination_channel = f64::from(destination.0[channel]);
+    let source_channel = match mode {
+      // Multiplying only applies where there is a destination to darken.
+      BlendMode::Multiply => {
+        let multiplied = f64::from(source.0[channel]) * destination_channel / 255.0;
+        multiplied * destination_alpha + f64::from(source.0[channel]) * (1.0 - destination_alpha)
+      }
+      _ => f64::from(source.0[channel]),
+    };
+    let blended = if output_alpha > 0.0 {
+      (source_channel * alpha + destination_channel * destination_alpha * (1.0 - alpha))
+        / output_alpha
+    } else {
+      0.0
+    };
+    destination.0[channel] = blended.round() as u8;
+  }
+  destination.0[3] = (output_alpha * 255.0).round() as u8;
+}
+
+/// Composites one image onto another with its top-left at the given position,
+/// clipping whatever falls outside the destination.
+pub fn paste(
+  destination: &mut RgbaImage,
+  source: &RgbaImage,
+  origin: (i64, i64),
+  options: Option<CompositeOptions>,
+) {
+  let mode = options.and_then(|options| options.mode).unwrap_or_default();
+  let opacity = options.and_then(|options| options.opacity).unwrap_or(1.0);
+
+  for (x, y, pixel) in source.enumerate_pixels() {
+    let (target_x, target_y) = (origin.0 + i64::from(x), origin.1 + i64::from(y));
+    if target_x < 0
+      || target_y < 0
+      || target_x >= i64::from(destination.width())
+      || target_y >= i64::from(destination.height())
+    {
+      continue;
+    }
+
+    composite_pixel(
+      destination.get_pixel_mut(target_x as u32, target_y as u32),
+      *pixel,
+      mode,
+      opacity,
+    );
+  }
+}
+
+pub fn fill(
+  destination: &mut RgbaImage,
+  region: Option<Region>,
+  rgba: u32,
+  options: Option<CompositeOptions>,
+) {
+  let region = Region::within(region, destination);
+  let mode = options.and_then(|options| options.mode).unwrap_or_default();
+  let opacity = options.and_then(|options| options.opacity).unwrap_or(1.0);
+  let colour = rgba_number_into_rgba(rgba);
+
+  for y in region.y..region.y + region.height {
+    for x in region.x..region.x + region.width {
+      composite_pixel(destination.get_pixel_mut(x, y), colour, mode, opacity);
+    }
+  }
+}
@@ -0,0 +1,598 @@
+use image::{Rgba, RgbaImage};
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::image::{
+  colour::Colour,
+  compositing::{composite_pixel, BlendMode},
+  rgba_number_into_rgba, Pixel, Region,
+};
+
+const GLYPH_WIDTH: i64 = 5;
+const GLYPH_HEIGHT: i64 = 7;
+const GLYPH_ADVANCE: i64 = GLYPH_WIDTH + 1;
+const LINE_ADVANCE: i64 = GLYPH_HEIGHT + 1;
+const MAX_THICKNESS: u32 = 100;
+
+/// 5x7 glyphs for ASCII space to underscore, one row per byte with the
+/// leftmost column in the highest of the five bits. Lowercase letters are drawn
+/// with their uppercase glyphs and anything else falls back to `?`.
+const FONT: [[u8; 7]; 64] = [
+  [
+    0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000,
+  ], // space
+  [
+    0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100,
+  ], // !
+  [
+    0b01010, 0b01010, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000,
+  ], // "
+  [
+    0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010,
+  ], // #
+  [
+    0b00100, 0b01111, 0b10100, 0b01110, 0b00101, 0b11110, 0b00100,
+  ], // $
+  [
+    0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011,
+  ], // %
+  [
+    0b01100, 0b10010, 0b10100, 0b01000, 0b10101, 0b10010, 0b01101,
+  ], // &
+  [
+    0b00100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000,
+  ], // '
+  [
+    0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010,
+  ], // (
+  [
+    0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000,
+  ], // )
+  [
+    0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000,
+  ], // *
+  [
+    0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000,
+  ], // +
+  [
+    0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000,
+  ], // ,
+  [
+    0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000,
+  ], // -
+  [
+    0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100,
+  ], // .
+  [
+    0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000,
+  ], // /
+  [
+    0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110,
+  ], // 0
+  [
+    0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+  ], // 1
+  [
+    0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111,
+  ], // 2
+  [
+    0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110,
+  ], // 3
+  [
+    0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010,
+  ], // 4
+  [
+    0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110,
+  ], // 5
+  [
+    0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110,
+  ], // 6
+  [
+    0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000,
+  ], // 7
+  [
+    0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110,
+  ], // 8
+  [
+    0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100,
+  ], // 9
+  [
+    0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000,
+  ], // :
+  [
+    0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b00100, 0b01000,
+  ], // ;
+  [
+    0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010,
+  ], // <
+  [
+    0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000,
+  ], // =
+  [
+    0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000,
+  ], // >
+  [
+    0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100,
+  ], // ?
+  [
+    0b01110, 0b10001, 0b00001, 0b01101, 0b10101, 0b10101, 0b01110,
+  ], // @
+  [
+    0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+  ], // A
+  [
+    0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110,
+  ], // B
+  [
+    0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110,
+  ], // C
+  [
+    0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100,
+  ], // D
+  [
+    0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111,
+  ], // E
+  [
+    0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000,
+  ], // F
+  [
+    0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111,
+  ], // G
+  [
+    0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+  ], // H
+  [
+    0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+  ], // I
+  [
+    0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100,
+  ], // J
+  [
+    0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001,
+  ], // K
+  [
+    0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111,
+  ], // L
+  [
+    0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001,
+  ], // M
+  [
+    0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001,
+  ], // N
+  [
+    0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+  ], // O
+  [
+    0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000,
+  ], // P
+  [
+    0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101,
+  ], // Q
+  [
+    0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001,
+  ], // R
+  [
+    0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110,
+  ], // S
+  [
+    0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+  ], // T
+  [
+    0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+  ], // U
+  [
+    0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+  ], // V
+  [
+    0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010,
+  ], // W
+  [
+    0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001,
+  ], // X
+  [
+    0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100,
+  ], // Y
+  [
+    0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111,
+  ], // Z
+  [
+    0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110,
+  ], // [
+  [
+    0b00000, 0b10000, 0b01000, 0b00100, 0b00010, 0b00001, 0b00000,
+  ], // backslash
+  [
+    0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110,
+  ], // ]
+  [
+    0b00100, 0b01010, 0b10001, 0b00000, 0b00000, 0b00000, 0b00000,
+  ], // ^
+  [
+    0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111,
+  ], // _
+];
+
+#[napi(object)]
+#[derive(Clone, Copy, Default)]
+pub struct StrokeOptions {
+  /// Width of lines and outlines in pixels. Defaults to 1, and is capped at 100.
+  pub thickness: Option<u32>,
+  /// Whether rectangles and circles are filled rather than outlined.
+  pub filled: Option<bool>,
+}
+
+impl StrokeOptions {
+  fn thickness(options: Option<StrokeOptions>) -> i64 {
+    i64::from(
+      options
+        .and_then(|options| options.thickness)
+        .unwrap_or(1)
+        .clamp(1, MAX_THICKNESS),
+    )
+  }
+
+  fn filled(options: Option<StrokeOptions>) -> bool {
+    options.and_then(|options| options.filled).unwrap_or(false)
+  }
+}
+
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct TextOptions {
+  /// Size of each font pixel in image pixels. Defaults to 1.
+  pub scale: Option<u32>,
+  /// Colour filled behind the text, to keep labels readable on busy captures.
+  pub background: Option<Colour>,
+}
+
+/// Composites a colour over the pixel at the given position using its alpha,
+/// ignoring positions outside the image.
+fn blend_pixel(rgba_image: &mut RgbaImage, x: i64, y: i64, colour: Rgba<u8>, coverage: f64) {
+  if x < 0 || y < 0 || x >= i64::from(rgba_image.width()) || y >= i64::from(rgba_image.height()) {
+    return;
+  }
+
+  composite_pixel(
+    rgba_image.get_pixel_mut(x as u32, y as u32),
+    colour,
+    BlendMode::Over,
+    coverage,
+  );
+}
+
+/// Blends a set of points once each, so overlapping strokes of a translucent
+/// colour do not darken where they cross.
+fn blend_points(rgba_image: &mut RgbaImage, points: HashSet<(i64, i64)>, rgba: u32) {
+  let colour = rgba_number_into_rgba(rgba);
+  for (x, y) in points {
+    blend_pixel(rgba_image, x, y, colour, 1.0);
+  }
+}
+
+/// The positions along an axis, from `start` for `length` pixels, that lie
+/// within an image dimension.
+fn clip_span(start: i64, length: i64, limit: u32) -> Range<i64> {
+  start.max(0)..start.saturating_add(length).min(i64::from(limit))
+}
+
+/// Clips a line to a rectangle with the Liang-Barsky algorithm, returning
+/// `None` if none of it lies inside.
+fn clip_line(
+  start: (i64, i64),
+  end: (i64, i64),
+  min: (i64, i64),
+  max: (i64, i64),
+) -> Option<((i64, i64), (i64, i64))> {
+  let (x0, y0) = (start.0 as f64, start.1 as f64);
+  let (dx, dy) = ((end.0 - start.0) as f64, (end.1 - start.1) as f64);
+  let (mut t0, mut t1) = (0.0f64, 1.0f64);
+
+  for (p, q) in [
+    (-dx, x0 - min.0 as f64),
+    (dx, max.0 as f64 - x0),
+    (-dy, y0 - min.1 as f64),
+    (dy, max.1 as f64 - y0),
+  ] {
+    if p == 0.0 {
+      if q < 0.0 {
+        return None;
+      }
+    } else {
+      let t = q / p;
+      if p < 0.0 {
+        t0 = t0.max(t);
+      } else {
+        t1 = t1.min(t);
+      }
+    }
+  }
+
+  if t0 > t1 {
+    return None;
+  }
+  let point_at = |t: f64| ((x0 + t * dx).round() as i64, (y0 + t * dy).round() as i64);
+  Some((point_at(t0), point_at(t1)))
+}
+
+/// Adds the pixels of a line of the given thickness that fall within the image
+/// to a set of points. The line is clipped first, so off-screen lengths cost
+/// nothing.
+fn line_points(
+  points: &mut HashSet<(i64, i64)>,
+  start: (i64, i64),
+  end: (i64, i64),
+  thickness: i64,
+  (width, height): (u32, u32),
+) {
+  let Some((start, end)) = clip_line(
+    start,
+    end,
+    (-thickness, -thickness),
+    (i64::from(width) + thickness, i64::from(height) + thickness),
+  ) else {
+    return;
+  };
+
+  let (mut x, mut y) = start;
+  let dx = (end.0 - x).abs();
+  let dy = -(end.1 - y).abs();
+  let step_x = if x < end.0 { 1 } else { -1 };
+  let step_y = if y < end.1 { 1 } else { -1 };
+  let mut error = dx + dy;
+  let offset = (thickness - 1) / 2;
+
+  loop {
+    for brush_y in clip_span(y - offset, thickness, height) {
+      for brush_x in clip_span(x - offset, thickness, width) {
+        points.insert((brush_x, brush_y));
+      }
+    }
+
+    if (x, y) == end {
+      break;
+    }
+
+    let doubled_error = 2 * error;
+    if doubled_error >= dy {
+      error += dy;
+      x += step_x;
+    }
+    if doubled_error <= dx {
+      error += dx;
+      y += step_y;
+    }
+  }
+}
+
+pub fn draw_line(
+  rgba_image: &mut RgbaImage,
+  start: (i64, i64),
+  end: (i64, i64),
+  rgba: u32,
+  options: Option<StrokeOptions>,
+) {
+  let mut points = HashSet::new();
+  line_points(
+    &mut points,
+    start,
+    end,
+    StrokeOptions::thickness(options),
+    rgba_image.dimensions(),
+  );
+  blend_points(rgba_image, points, rgba);
+}
+
+pub fn draw_rectangle(
+  rgba_image: &mut RgbaImage,
+  region: Region,
+  rgba: u32,
+  options: Option<StrokeOptions>,
+) {
+  draw_box(
+    rgba_image,
+    (region.x.into(), region.y.into()),
+    (region.width.into(), region.height.into()),
+    rgba,
+    options,
+  );
+}
+
+/// Draws a rectangle that may start above or left of the image. Only the part
+/// within the image is visited.
+fn draw_box(
+  rgba_image: &mut RgbaImage,
+  (left, top): (i64, i64),
+  (width, height): (i64, i64),
+  rgba: u32,
+  options: Option<StrokeOptions>,
+) {
+  let thickness = StrokeOptions::thickness(options);
+  let filled = StrokeOptions::filled(options);
+  let colour = rgba_number_into_rgba(rgba);
+  let (Some(right), Some(bottom)) = (left.checked_add(width), top.checked_add(height)) else {
+    return;
+  };
+
+  for y in clip_span(top, height, rgba_image.height()) {
+    for x in clip_span(left, width, rgba_image.width()) {
+      let inset = (x - left)
+        .min(y - top)
+        .min(right - 1 - x)
+        .min(bottom - 1 - y);
+      if filled || inset < thickness {
+        blend_pixel(rgba_image, x, y, colour, 1.0);
+      }
+    }
+  }
+}
+
+pub fn draw_circle(
+  rgba_image: &mut RgbaImage,
+  centre: (i64, i64),
+  radius: u32,
+  rgba: u32,
+  options: Option<StrokeOptions>,
+) {
+  let thickness = StrokeOptions::thickness(options) as f64;
+  let filled = StrokeOptions::filled(options);
+  let colour = rgba_number_into_rgba(rgba);
+  let radius_f = f64::from(radius);
+  let extent = i64::from(radius);
+
+  for y in clip_span(centre.1 - extent, 2 * extent + 1, rgba_image.height()) {
+    for x in clip_span(centre.0 - extent, 2 * extent + 1, rgba_image.width()) {
+      let distance = ((x - centre.0) as f64).hypot((y - centre.1) as f64);
+      if distance <= radius_f + 0.5 && (filled || distance > radius_f + 0.5 - thickness) {
+        blend_pixel(rgba_image, x, y, colour, 1.0);
+      }
+    }
+  }
+}
+
+pub fn draw_crosshair(
+  rgba_image: &mut RgbaImage,
+  centre: (i64, i64),
+  size: u32,
+  rgba: u32,
+  options: Option<StrokeOptions>,
+) {
+  let thickness = StrokeOptions::thickness(options);
+  let half = i64::from(size) / 2;
+  let dimensions = rgba_image.dimensions();
+  let mut points = HashSet::new();
+  line_points(
+    &mut points,
+    (centre.0 - half, centre.1),
+    (centre.0 + half, centre.1),
+    thickness,
+    dimensions,
+  );
+  line_points(
+    &mut points,
+    (centre.0, centre.1 - half),
+    (centre.0, centre.1 + half),
+    thickness,
+    dimensions,
+  );
+  blend_points(rgba_image, points, rgba);
+}
+
+fn glyph(character: char) -> &'static [u8; 7] {
+  let code = character.to_ascii_uppercase() as u32;
+  match code {
+    32..=95 => &FONT[(code - 32) as usize],
+    _ => &FONT[('?' as u32 - 32) as usize],
+  }
+}
+
+/// Draws text with its top-left at the given position. Newlines start a new
+/// line below the first.
+pub fn draw_text(
+  rgba_image: &mut RgbaImage,
+  origin: (i64, i64),
+  text: &str,
+  rgba: u32,
+  scale: u32,
+  background: Option<u32>,
+) {
+  let scale = i64::from(scale.max(1));
+
+  if let Some(background) = background {
+    let lines = text.split('\n');
+    let columns = lines
+      .clone()
+      .map(|line| line.chars().count())
+      .max()
+      .unwrap_or(0) as i64;
+    let rows = lines.count() as i64;
+    if columns > 0 {
+      let extent = |count: i64, advance: i64| {
+        count
+          .saturating_mul(advance)
+          .saturating_add(1)
+          .saturating_mul(scale)
+      };
+      draw_box(
+        rgba_image,
+        (origin.0 - scale, origin.1 - scale),
+        (extent(columns, GLYPH_ADVANCE), extent(rows, LINE_ADVANCE)),
+        background,
+        Some(StrokeOptions {
+          thickness: None,
+          filled: Some(true),
+        }),
+      );
+    }
+  }
+
+  let (width, height) = rgba_image.dimensions();
+  let mut points = HashSet::new();
+  for (row, line) in text.split('\n').enumerate() {
+    let glyph_y = origin
+      .1
+      .saturating_add((row as i64).saturating_mul(LINE_ADVANCE * scale));
+    if glyph_y >= i64::from(height) {
+      break;
+    }
+
+    for (column, character) in line.chars().enumerate() {
+      let glyph_x = origin
+        .0
+        .saturating_add((column as i64).saturating_mul(GLYPH_ADVANCE * scale));
+      if glyph_x >= i64::from(width) {
+        break;
+      }
+
+      for (glyph_row, bits) in glyph(character).iter().enumerate() {
+        for glyph_column in 0..GLYPH_WIDTH {
+          if bits & (1 << (GLYPH_WIDTH - 1 - glyph_column)) == 0 {
+            continue;
+          }
+          for y in clip_span(glyph_y + glyph_row as i64 * scale, scale, height) {
+            for x in clip_span(glyph_x + glyph_column * scale, scale, width) {
+              points.insert((x, y));
+            }
+          }
+        }
+      }
+    }
+  }
+  blend_points(rgba_image, points, rgba);
+}
+
+/// Tints the image wherever the mask is opaque, scaling the colour's alpha by
+/// the mask's alpha. The mask's top-left is placed at the given position.
+pub fn overlay_mask(rgba_image: &mut RgbaImage, mask: &RgbaImage, origin: (i64, i64), rgba: u32) {
+  let colour = rgba_number_into_rgba(rgba);
+  for (x, y, pixel) in mask.enumerate_pixels() {
+    if pixel.0[3] > 0 {
+      blend_pixel(
+        rgba_image,
+        origin.0 + i64::from(x),
+        origin.1 + i64::from(y),
+        colour,
+        f64::from(pixel.0[3]) / 255.0,
+      );
+    }
+  }
+}
+
+/// Draws pixels such as those returned by `findRgbas` or held by a feature,
+/// offset by the given position, in their own colour or in a single colour.
+pub fn draw_pixels(
+  rgba_image: &mut RgbaImage,
+  pixels: &[Pixel],
+  origin: (i64, i64),
+  rgba: Option<u32>,
+) {
+  for pixel in pixels {
+    let colour = rgba_number_into_rgba(rgba.unwrap_or(pixel.rgba));
+    blend_pixel(
+      rgba_image,
+      origin.0 + i64::from(pixel.x),
+      origin.1 + i64::from(pixel.y),
+      colour,
+      1.0,
+    );
+  }
+}
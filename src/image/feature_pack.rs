@@ -0,0 +1,450 @@
+use napi::{bindgen_prelude::AsyncTask, bindgen_prelude::Buffer, Env, Error, Task};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::image::{Feature, FeatureAnchor, Pixel};
+
+const FEATURE_MAGIC: &[u8; 4] = b"HXFT";
+const FEATURE_FORMAT_VERSION: u16 = 1;
+const FEATURE_HEADER_LENGTH: usize = 22;
+const FEATURE_FILE_EXTENSION: &str = "hxf";
+/// Largest bounding box a feature may declare, so corrupt headers can't make
+/// decoding spin over billions of cells.
+const MAX_FEATURE_CELLS: u64 = 1 << 24;
+
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum FeaturePackError {
+  Io(String),
+  InvalidManifest(String),
+  UnsupportedManifestVersion(u32),
+  UnsupportedFeatureVersion(u16),
+  InvalidFeatureData(&'static str),
+  InvalidFeatureName(String),
+  DuplicateFeatureName(String),
+}
+
+impl From<std::io::Error> for FeaturePackError {
+  fn from(value: std::io::Error) -> Self {
+    FeaturePackError::Io(value.to_string())
+  }
+}
+
+impl From<serde_json::Error> for FeaturePackError {
+  fn from(value: serde_json::Error) -> Self {
+    FeaturePackError::InvalidManifest(value.to_string())
+  }
+}
+
+impl From<FeaturePackError> for Error {
+  fn from(value: FeaturePackError) -> Error {
+    Error::from_reason(format!("{:?}", value))
+  }
+}
+
+#[napi(object)]
+pub struct NamedFeature {
+  pub name: String,
+  pub feature: Feature,
+  pub color_tolerance: Option<f64>,
+  pub pixel_tolerance: Option<f64>,
+}
+
+#[napi(object)]
+pub struct FeaturePack {
+  pub features: Vec<NamedFeature>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+  version: u32,
+  features: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ManifestEntry {
+  name: String,
+  file: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  anchor: Option<FeatureAnchor>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  color_tolerance: Option<f64>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pixel_tolerance: Option<f64>,
+}
+
+/// Encodes a feature into the compact `HXFT` binary format.
+///
+/// The layout is a fixed header (magic, format version, origin and size of the
+/// bounding box) followed by run-length encoded cells of the bounding box in
+/// row-major order. Each run is a LEB128 length and a tag byte, `0` for cells
+/// that are not part of the feature and `1` followed by the big-endian RGBA
+/// value for cells that are. Bounding boxes over 2^24 cells are rejected.
+pub fn encode_feature_bytes(feature: &Feature) -> Result<Vec<u8>, FeaturePackError> {
+  let mut bytes = Vec::with_capacity(FEATURE_HEADER_LENGTH);
+  bytes.extend_from_slice(FEATURE_MAGIC);
+  bytes.extend_from_slice(&FEATURE_FORMAT_VERSION.to_le_bytes());
+
+  if feature.pixels.is_empty() {
+    bytes.extend_from_slice(&[0; 16]);
+    return Ok(bytes);
+  }
+
+  let min_x = feature.pixels.iter().map(|p| p.x).min().unwrap();
+  let min_y = feature.pixels.iter().map(|p| p.y).min().unwrap();
+  let max_x = feature.pixels.iter().map(|p| p.x).max().unwrap();
+  let max_y = feature.pixels.iter().map(|p| p.y).max().unwrap();
+  let width = max_x - min_x + 1;
+  let height = max_y - min_y + 1;
+  if u64::from(width) * u64::from(height) > MAX_FEATURE_CELLS {
+    return Err(FeaturePackError::InvalidFeatureData(
+      "Feature bounds are too large",
+    ));
+  }
+
+  bytes.extend_from_slice(&min_x.to_le_bytes());
+  bytes.extend_from_slice(&min_y.to_le_bytes());
+  bytes.extend_from_slice(&width.to_le_bytes());
+  bytes.extend_from_slice(&height.to_le_bytes());
+
+  let cells: BTreeMap<(u32, u32), u32> = feature
+    .pixels
+    .iter()
+    .map(|p| ((p.y - min_y, p.x - min_x), p.rgba))
+    .collect();
+
+  let mut current: Option<Option<u32>> = None;
+  let mut run_length: u64 = 0;
+
+  for y in 0..height {
+    for x in 0..width {
+      let cell = cells.get(&(y, x)).copied();
+
+      if current == Some(cell) {
+        run_length += 1;
+        continue;
+      }
+
+      if let Some(previous) = current {
+        write_run(&mut bytes, run_length, previous);
+      }
+
+      current = Some(cell);
+      run_length = 1;
+    }
+  }
+
+  if let Some(previous) = current {
+    write_run(&mut bytes, run_length, previous);
+  }
+
+  Ok(bytes)
+}
+
+/// Decodes a feature previously produced by [`encode_feature_bytes`]. Anchors
+/// are not part of the binary format; feature packs keep them in the manifest.
+pub fn decode_feature_bytes(bytes: &[u8]) -> Result<Feature, FeaturePackError> {
+  if bytes.len() < FEATURE_HEADER_LENGTH || &bytes[0..4] != FEATURE_MAGIC {
+    return Err(FeaturePackError::InvalidFeatureData(
+      "Missing feature header",
+    ));
+  }
+
+  let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+  if version != FEATURE_FORMAT_VERSION {
+    return Err(FeaturePackError::UnsupportedFeatureVersion(version));
+  }
+
+  let read_u32 = |offset: usize| {
+    u32::from_le_bytes([
+      bytes[offset],
+      bytes[offset + 1],
+      bytes[offset + 2],
+      bytes[offset + 3],
+    ])
+  };
+  let origin_x = read_u32(6);
+  let origin_y = read_u32(10);
+  let width = read_u32(14);
+  let height = read_u32(18);
+
+  let total_cells = (width as u64) * (height as u64);
+  if total_cells > MAX_FEATURE_CELLS {
+    return Err(FeaturePackError::InvalidFeatureData(
+      "Feature bounds are too large",
+    ));
+  }
+
+  let mut pixels = Vec::new();
+  let mut cell_index: u64 = 0;
+  let mut cursor = FEATURE_HEADER_LENGTH;
+
+  while cell_index < total_cells {
+    let run_length = read_varint(bytes, &mut cursor)?;
+    if run_length == 0 || cell_index + run_length > total_cells {
+      return Err(FeaturePackError::InvalidFeatureData(
+        "Run exceeds feature bounds",
+      ));
+    }
+
+    let tag = *bytes
+      .get(cursor)
+      .ok_or(FeaturePackError::InvalidFeatureData("Truncated run"))?;
+    cursor += 1;
+
+    match tag {
+      0 => {}
+      1 => {
+        let rgba_bytes = bytes
+          .get(cursor..cursor + 4)
+          .ok_or(FeaturePackError::InvalidFeatureData("Truncated run colour"))?;
+        let rgba = u32::from_be_bytes([rgba_bytes[0], rgba_bytes[1], rgba_bytes[2], rgba_bytes[3]]);
+        cursor += 4;
+
+        for index in cell_index..(cell_index + run_length) {
+          let x = origin_x.checked_add((index % width as u64) as u32);
+          let y = origin_y.checked_add((index / width as u64) as u32);
+          let (Some(x), Some(y)) = (x, y) else {
+            return Err(FeaturePackError::InvalidFeatureData(
+              "Pixel position out of range",
+            ));
+          };
+          pixels.push(Pixel { x, y, rgba });
+        }
+      }
+      _ => return Err(FeaturePackError::InvalidFeatureData("Unknown run tag")),
+    }
+
+    cell_index += run_length;
+  }
+
+  if cursor != bytes.len() {
+    return Err(FeaturePackError::InvalidFeatureData(
+      "Trailing bytes after feature data",
+    ));
+  }
+
+  Ok(Feature {
+    pixels,
+    anchor: None,
+  })
+}
+
+fn write_run(bytes: &mut Vec<u8>, run_length: u64, cell: Option<u32>) {
+  write_varint(bytes, run_length);
+  match cell {
+    Some(rgba) => {
+      bytes.push(1);
+      bytes.extend_from_slice(&rgba.to_be_bytes());
+    }
+    None => bytes.push(0),
+  }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+  loop {
+    let byte = (value & 0x7F) as u8;
+    value >>= 7;
+    if value == 0 {
+      bytes.push(byte);
+      return;
+    }
+    bytes.push(byte | 0x80);
+  }
+}
+
+fn read_varint(bytes: &[u8], cursor: &mut usize) -> Result<u64, FeaturePackError> {
+  let mut value: u64 = 0;
+
+  for shift in (0..64).step_by(7) {
+    let byte = *bytes
+      .get(*cursor)
+      .ok_or(FeaturePackError::InvalidFeatureData("Truncated run length"))?;
+    *cursor += 1;
+    value |= ((byte & 0x7F) as u64) << shift;
+
+    if byte & 0x80 == 0 {
+      return Ok(value);
+    }
+  }
+
+  Err(FeaturePackError::InvalidFeatureData("Run length too long"))
+}
+
+fn validate_feature_name(name: &str) -> Result<(), FeaturePackError> {
+  let is_valid = !name.is_empty()
+    && name
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    && !name.starts_with('.');
+
+  if is_valid {
+    Ok(())
+  } else {
+    Err(FeaturePackError::InvalidFeatureName(name.to_string()))
+  }
+}
+
+fn resolve_feature_file(directory: &Path, file: &str) -> Result<PathBuf, FeaturePackError> {
+  let relative = Path::new(file);
+  let is_plain_relative = relative
+    .components()
+    .all(|component| matches!(component, std::path::Component::Normal(_)));
+
+  if !is_plain_relative {
+    return Err(FeaturePackError::InvalidManifest(format!(
+      "Feature file `{}` must be a relative path inside the pack",
+      file
+    )));
+  }
+
+  Ok(directory.join(relative))
+}
+
+#[napi]
+pub fn encode_feature(feature: Feature) -> Result<Buffer, Error> {
+  Ok(encode_feature_bytes(&feature)?.into())
+}
+
+#[napi]
+pub fn decode_feature(bytes: Buffer) -> Result<Feature, Error> {
+  Ok(decode_feature_bytes(&bytes)?)
+}
+
+#[napi(ts_return_type = "Promise<FeaturePack>")]
+pub fn load_feature_pack(directory: String) -> AsyncTask<AsyncLoadFeaturePack> {
+  AsyncTask::new(AsyncLoadFeaturePack::new(directory))
+}
+
+#[napi(ts_return_type = "Promise<void>")]
+pub fn save_feature_pack(directory: String, pack: FeaturePack) -> AsyncTask<AsyncSaveFeaturePack> {
+  AsyncTask::new(AsyncSaveFeaturePack::new(directory, pack))
+}
+
+pub struct AsyncLoadFeaturePack {
+  directory: PathBuf,
+}
+
+impl AsyncLoadFeaturePack {
+  pub fn new(directory: String) -> Self {
+    Self {
+      directory: PathBuf::from(directory),
+    }
+  }
+}
+
+#[napi]
+impl Task for AsyncLoadFeaturePack {
+  type Output = FeaturePack;
+  type JsValue = FeaturePack;
+
+  fn compute(&mut self) -> Result<Self::Output, Error> {
+    let manifest_json = fs::read_to_string(self.directory.join(MANIFEST_FILE_NAME))
+      .map_err(FeaturePackError::from)?;
+    let manifest: Manifest =
+      serde_json::from_str(&manifest_json).map_err(FeaturePackError::from)?;
+
+    if manifest.version != MANIFEST_VERSION {
+      return Err(FeaturePackError::UnsupportedManifestVersion(manifest.version).into());
+    }
+
+    let mut seen_names = HashSet::new();
+    let mut features = Vec::with_capacity(manifest.features.len());
+
+    for entry in manifest.features {
+      if !seen_names.insert(entry.name.clone()) {
+        return Err(FeaturePackError::DuplicateFeatureName(entry.name).into());
+      }
+
+      let path = resolve_feature_file(&self.directory, &entry.file)?;
+      let bytes = fs::read(path).map_err(FeaturePackError::from)?;
+
+      let mut feature = decode_feature_bytes(&bytes)?;
+      feature.anchor = entry.anchor;
+
+      features.push(NamedFeature {
+        name: entry.name,
+        feature,
+        color_tolerance: entry.color_tolerance,
+        pixel_tolerance: entry.pixel_tolerance,
+      });
+    }
+
+    Ok(FeaturePack { features })
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
+    Ok(output)
+  }
+}
+
+pub struct AsyncSaveFeaturePack {
+  directory: PathBuf,
+  pack: FeaturePack,
+}
+
+impl AsyncSaveFeaturePack {
+  pub fn new(directory: String, pack: FeaturePack) -> Self {
+    Self {
+      directory: PathBuf::from(directory),
+      pack,
+    }
+  }
+}
+
+#[napi]
+impl Task for AsyncSaveFeaturePack {
+  type Output = ();
+  type JsValue = ();
+
+  fn compute(&mut self) -> Result<Self::Output, Error> {
+    let mut seen_names = HashSet::new();
+    for named_feature in &self.pack.features {
+      validate_feature_name(&named_feature.name)?;
+
+      if !seen_names.insert(named_feature.name.as_str()) {
+        return Err(FeaturePackError::DuplicateFeatureName(named_feature.name.clone()).into());
+      }
+    }
+
+    fs::create_dir_all(&self.directory).map_err(FeaturePackError::from)?;
+
+    let mut entries = Vec::with_capacity(self.pack.features.len());
+    for named_feature in &self.pack.features {
+      let file = format!("{}.{}", named_feature.name, FEATURE_FILE_EXTENSION);
+      fs::write(
+        self.directory.join(&file),
+        encode_feature_bytes(&named_feature.feature)?,
+      )
+      .map_err(FeaturePackError::from)?;
+
+      entries.push(ManifestEntry {
+        name: named_feature.name.clone(),
+        file,
+        anchor: named_feature.feature.anchor,
+        color_tolerance: named_feature.color_tolerance,
+        pixel_tolerance: named_feature.pixel_tolerance,
+      });
+    }
+
+    let manifest = Manifest {
+      version: MANIFEST_VERSION,
+      features: entries,
+    };
+    let manifest_json = serde_json::to_string_pretty(&manifest).map_err(FeaturePackError::from)?;
+    fs::write(self.directory.join(MANIFEST_FILE_NAME), manifest_json)
+      .map_err(FeaturePackError::from)?;
+
+    Ok(())
+  }
+
+  fn resolve(&mut self, _env: Env, _output: Self::Output) -> Result<Self::JsValue, Error> {
+    Ok(())
+  }
+}
@@ -0,0 +1,186 @@
+use image::{Rgba, RgbaImage};
+use napi::{Env, Error, Task};
+
+use crate::image::{
+  color_distance, rgba_into_rgba_number, rgba_number_into_rgba, Cancellation, Region,
+};
+
+const MAX_COLOR_DISTANCE: f64 = 510.0; // Using alpha: sqrt(255^2 * 4)
+const DEFAULT_MIN_COVERAGE: f64 = 0.5;
+/// How far the gradient reference moves towards each filled row or column.
+/// Gradients are followed, but a gradual fade into the empty part of the bar
+/// isn't.
+const GRADIENT_ADAPTATION: f64 = 0.25;
+
+#[napi(string_enum)]
+#[derive(Clone, Copy, Debug)]
+pub enum FillDirection {
+  LeftToRight,
+  RightToLeft,
+  TopToBottom,
+  BottomToTop,
+}
+
+#[napi(object)]
+#[derive(Clone, Copy, Default)]
+pub struct FillOptions {
+  /// Fraction of a row or column across the bar that must match for it to
+  /// count as filled, so borders and overlaid text do not break the fill.
+  /// Defaults to 0.5.
+  pub min_coverage: Option<f64>,
+  /// Also accept colours close to a reference that slowly follows the filled
+  /// rows or columns, so fills drawn with a gradient are followed to their
+  /// end. Defaults to true.
+  pub follow_gradient: Option<bool>,
+  /// Number of consecutive unfilled rows or columns tolerated inside the fill
+  /// before it is considered to have ended, covering markers drawn over it.
+  /// Unfilled rows or columns before the fill starts are a border, whatever
+  /// their number. Defaults to the bar's thickness.
+  pub max_gap: Option<u32>,
+}
+
+pub struct AsyncMeasureFill {
+  rgba_image: RgbaImage,
+  region: Region,
+  rgba_number: u32,
+  direction: FillDirection,
+  max_color_distance_percent: f64,
+  options: FillOptions,
+  cancellation: Cancellation,
+}
+
+impl AsyncMeasureFill {
+  pub fn new(
+    rgba_image: RgbaImage,
+    region: Region,
+    rgba_number: u32,
+    direction: FillDirection,
+    max_color_distance_percent: f64,
+    options: Option<FillOptions>,
+    cancellation: Cancellation,
+  ) -> Self {
+    Self {
+      region: Region::within(Some(region), &rgba_image),
+      rgba_image,
+      rgba_number,
+      direction,
+      max_color_distance_percent,
+      options: options.unwrap_or_default(),
+      cancellation,
+    }
+  }
+
+  /// Pixel at a position along the fill axis and across it, both counted from
+  /// the region's edge the fill starts at.
+  fn pixel_at(&self, along: u32, across: u32) -> &Rgba<u8> {
+    let Region {
+      x,
+      y,
+      width,
+      height,
+    } = self.region;
+    let (px, py) = match self.direction {
+      FillDirection::LeftToRight => (x + along, y + across),
+      FillDirection::RightToLeft => (x + width - 1 - along, y + across),
+      FillDirection::TopToBottom => (x + across, y + along),
+      FillDirection::BottomToTop => (x + across, y + height - 1 - along),
+    };
+    self.rgba_image.get_pixel(px, py)
+  }
+}
+
+#[napi]
+impl Task for AsyncMeasureFill {
+  type Output = f64;
+  type JsValue = f64;
+
+  fn compute(&mut self) -> Result<Self::Output, Error> {
+    let (length, breadth) = match self.direction {
+      FillDirection::LeftToRight | FillDirection::RightToLeft => {
+        (self.region.width, self.region.height)
+      }
+      FillDirection::TopToBottom | FillDirection::BottomToTop => {
+        (self.region.height, self.region.width)
+      }
+    };
+    if length == 0 || breadth == 0 {
+      return Ok(0.0);
+    }
+
+    let max_distance = MAX_COLOR_DISTANCE * self.max_color_distance_percent;
+    let min_matching = (f64::from(breadth)
+      * self.options.min_coverage.unwrap_or(DEFAULT_MIN_COVERAGE))
+    .ceil()
+    .max(1.0) as u32;
+    let follow_gradient = self.options.follow_gradient.unwrap_or(true);
+    let max_gap = self.options.max_gap.unwrap_or(breadth);
+
+    let mut reference = rgba_number_into_rgba(self.rgba_number).0.map(f64::from);
+    let mut reference_number = self.rgba_number;
+    let mut border_length = None;
+    let mut filled_length = 0;
+    let mut gap = 0;
+
+    for along in 0..length {
+      self.cancellation.check()?;
+
+      let mut matching = 0;
+      let mut channel_sums = [0u64; 4];
+      for across in 0..breadth {
+        let pixel = self.pixel_at(along, across);
+        let pixel_number = rgba_into_rgba_number(pixel);
+        if color_distance(pixel_number, self.rgba_number, true) <= max_distance
+          || (follow_gradient
+            && color_distance(pixel_number, reference_number, true) <= max_distance)
+        {
+          matching += 1;
+          for (sum, channel) in channel_sums.iter_mut().zip(pixel.0) {
+            *sum += u64::from(channel);
+          }
+        }
+      }
+
+      if matching < min_matching {
+        // The border before the fill starts can be any width.
+        if border_length.is_some() {
+          gap += 1;
+          if gap > max_gap {
+            break;
+          }
+        }
+        continue;
+      }
+
+      border_length.get_or_insert(along);
+      filled_length = along + 1;
+      gap = 0;
+      if follow_gradient {
+        for (channel, sum) in reference.iter_mut().zip(channel_sums) {
+          let mean = sum as f64 / f64::from(matching);
+          *channel += (mean - *channel) * GRADIENT_ADAPTATION;
+        }
+        reference_number =
+          rgba_into_rgba_number(&Rgba(reference.map(|channel| channel.round() as u8)));
+      }
+    }
+
+    // Unfilled rows or columns before the fill are a border, which the far
+    // end of the bar is assumed to have too.
+    let Some(border_length) = border_length else {
+      return Ok(0.0);
+    };
+    let filled = filled_length - border_length;
+    let track = length
+      .saturating_sub(border_length.saturating_mul(2))
+      .max(filled);
+    Ok(f64::from(filled) / f64::from(track))
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
+    Ok(output)
+  }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue, Error> {
+    self.cancellation.reject(&env, err)
+  }
+}
@@ -0,0 +1,290 @@
+use image::{Rgba, RgbaImage};
+use napi::{bindgen_prelude::AsyncTask, Env, Error, Task};
+use std::{
+  env, fs,
+  io::ErrorKind,
+  path::{Path, PathBuf},
+};
+
+use crate::image::{color_distance, rgba_into_rgba_number, Image, Region};
+
+const MAX_COLOR_DISTANCE: f64 = 510.0; // Using alpha: sqrt(255^2 * 4)
+const UPDATE_GOLDENS_VARIABLE: &str = "HEROX_UPDATE_GOLDENS";
+const DIFF_COLOUR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+/// Directory in the system's temporary directory that failure images are
+/// written to by default, so they don't end up beside checked-in goldens.
+const DEFAULT_OUTPUT_DIRECTORY: &str = "herox-image-matches";
+
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct ImageMatchOptions {
+  /// Colour distance, as a fraction of the maximum, within which two pixels are
+  /// considered equal. Defaults to 0.
+  pub tolerance: Option<f64>,
+  /// Fraction of pixels allowed to differ before the images no longer match.
+  /// Defaults to 0.
+  pub max_mismatch_percent: Option<f64>,
+  /// Directory the actual, expected and diff images are written to on failure.
+  /// Defaults to `herox-image-matches` in the system's temporary directory.
+  pub output_directory: Option<String>,
+  /// Overwrite the expected image with the actual one instead of comparing.
+  /// Defaults to whether the `HEROX_UPDATE_GOLDENS` environment variable is
+  /// set to `1` or `true`.
+  pub update: Option<bool>,
+}
+
+#[napi(object)]
+pub struct ImageMatchResult {
+  pub matched: bool,
+  /// Whether the expected image was written rather than compared against.
+  pub updated: bool,
+  pub dimensions_match: bool,
+  pub total_pixels: u32,
+  pub mismatched_pixels: u32,
+  pub mismatch_percent: f64,
+  /// Largest colour distance between two pixels, as a fraction of the maximum.
+  pub max_color_distance: f64,
+  /// Mean colour distance over all pixels, as a fraction of the maximum.
+  pub mean_color_distance: f64,
+  /// Bounding box of the mismatched pixels.
+  pub mismatch_bounds: Option<Region>,
+  /// Paths of the images written when the match failed.
+  pub actual_path: Option<String>,
+  pub expected_path: Option<String>,
+  pub diff_path: Option<String>,
+}
+
+fn update_requested() -> bool {
+  env::var(UPDATE_GOLDENS_VARIABLE)
+    .map(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+    .unwrap_or(false)
+}
+
+fn io_error(path: &Path, error: impl ToString) -> Error {
+  Error::from_reason(format!("{}: {}", path.display(), error.to_string()))
+}
+
+fn pixel_count(width: u32, height: u32) -> Result<u32, Error> {
+  u32::try_from(u64::from(width) * u64::from(height))
+    .map_err(|_| Error::from_reason(format!("A {width}x{height} image is too large to compare")))
+}
+
+struct Comparison {
+  total_pixels: u32,
+  mismatched_pixels: u32,
+  max_distance: f64,
+  total_distance: f64,
+  bounds: Option<(u32, u32, u32, u32)>,
+  diff: RgbaImage,
+}
+
+/// Compares two images over the union of their sizes, treating pixels present
+/// in only one of them as mismatched. The diff shows the expected image faded
+/// to grey with mismatched pixels in red.
+fn compare(actual: &RgbaImage, expected: &RgbaImage, tolerance: f64) -> Result<Comparison, Error> {
+  let width = actual.width().max(expected.width());
+  let height = actual.height().max(expected.height());
+  let max_matching_distance = MAX_COLOR_DISTANCE * tolerance;
+  let mut comparison = Comparison {
+    total_pixels: pixel_count(width, height)?,
+    mismatched_pixels: 0,
+    max_distance: 0.0,
+    total_distance: 0.0,
+    bounds: None,
+    diff: RgbaImage::new(width, height),
+  };
+
+  for y in 0..height {
+    for x in 0..width {
+      let actual_pixel = actual.get_pixel_checked(x, y);
+      let expected_pixel = expected.get_pixel_checked(x, y);
+
+      let distance = match (actual_pixel, expected_pixel) {
+        (Some(actual_pixel), Some(expected_pixel)) => color_distance(
+          rgba_into_rgba_number(actual_pixel),
+          rgba_into_rgba_number(expected_pixel),
+          true,
+        ),
+        _ => MAX_COLOR_DISTANCE,
+      };
+      comparison.max_distance = comparison.max_distance.max(distance);
+      comparison.total_distance += distance;
+
+      if distance > max_matching_distance {
+        comparison.mismatched_pixels += 1;
+        comparison.bounds = Some(match comparison.bounds {
+          Some((min_x, min_y, max_x, max_y)) => {
+            (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+          }
+          None => (x, y, x, y),
+        });
+        comparison.diff.put_pixel(x, y, DIFF_COLOUR);
+      } else if let Some(expected_pixel) = expected_pixel {
+        let [r, g, b, _] = expected_pixel.0;
+        let luma = (0.299 * f64::from(r) + 0.587 * f64::from(g) + 0.114 * f64::from(b)) as u8;
+        let faded = 255 - (255 - luma) / 4;
+        comparison
+          .diff
+          .put_pixel(x, y, Rgba([faded, faded, faded, 255]));
+      }
+    }
+  }
+
+  Ok(comparison)
+}
+
+pub struct AsyncExpectImageMatch {
+  actual: RgbaImage,
+  expected_path: PathBuf,
+  options: ImageMatchOptions,
+}
+
+impl AsyncExpectImageMatch {
+  fn write_golden(&self) -> Result<ImageMatchResult, Error> {
+    if let Some(parent) = self.expected_path.parent() {
+      fs::create_dir_all(parent).map_err(|error| io_error(parent, error))?;
+    }
+    self
+      .actual
+      .save(&self.expected_path)
+      .map_err(|error| io_error(&self.expected_path, error))?;
+
+    Ok(ImageMatchResult {
+      matched: true,
+      updated: true,
+      dimensions_match: true,
+      total_pixels: pixel_count(self.actual.width(), self.actual.height())?,
+      mismatched_pixels: 0,
+      mismatch_percent: 0.0,
+      max_color_distance: 0.0,
+      mean_color_distance: 0.0,
+      mismatch_bounds: None,
+      actual_path: None,
+      expected_path: None,
+      diff_path: None,
+    })
+  }
+
+  /// Writes the actual, expected and diff images to the output directory,
+  /// named after the expected image.
+  fn write_failure_images(
+    &self,
+    expected: &RgbaImage,
+    diff: &RgbaImage,
+  ) -> Result<(String, String, String), Error> {
+    let directory = match &self.options.output_directory {
+      Some(directory) => PathBuf::from(directory),
+      None => env::temp_dir().join(DEFAULT_OUTPUT_DIRECTORY),
+    };
+    fs::create_dir_all(&directory).map_err(|error| io_error(&directory, error))?;
+
+    let stem = self
+      .expected_path
+      .file_stem()
+      .map(|stem| stem.to_string_lossy().into_owned())
+      .unwrap_or_else(|| "image".to_string());
+
+    let write = |suffix: &str, image: &RgbaImage| -> Result<String, Error> {
+      let path = directory.join(format!("{stem}.{suffix}.png"));
+      image.save(&path).map_err(|error| io_error(&path, error))?;
+      Ok(path.to_string_lossy().into_owned())
+    };
+
+    let actual_path = write("actual", &self.actual)?;
+    let expected_path = write("expected", expected)?;
+    let diff_path = write("diff", diff)?;
+    Ok((actual_path, expected_path, diff_path))
+  }
+}
+
+#[napi]
+impl Task for AsyncExpectImageMatch {
+  type Output = ImageMatchResult;
+  type JsValue = ImageMatchResult;
+
+  fn compute(&mut self) -> Result<Self::Output, Error> {
+    if self.options.update.unwrap_or_else(update_requested) {
+      return self.write_golden();
+    }
+
+    let expected = match image::open(&self.expected_path) {
+      Ok(expected) => expected.to_rgba8(),
+      Err(image::ImageError::IoError(error)) if error.kind() == ErrorKind::NotFound => {
+        return Err(io_error(
+          &self.expected_path,
+          format!("golden image missing; set `update` or {UPDATE_GOLDENS_VARIABLE}=1 to write it"),
+        ));
+      }
+      Err(error) => return Err(io_error(&self.expected_path, error)),
+    };
+
+    let comparison = compare(
+      &self.actual,
+      &expected,
+      self.options.tolerance.unwrap_or(0.0),
+    )?;
+    let dimensions_match = self.actual.dimensions() == expected.dimensions();
+    let mismatch_percent = if comparison.total_pixels == 0 {
+      0.0
+    } else {
+      f64::from(comparison.mismatched_pixels) / f64::from(comparison.total_pixels)
+    };
+    let matched =
+      dimensions_match && mismatch_percent <= self.options.max_mismatch_percent.unwrap_or(0.0);
+
+    let (actual_path, expected_path, diff_path) = if matched {
+      (None, None, None)
+    } else {
+      let (actual_path, expected_path, diff_path) =
+        self.write_failure_images(&expected, &comparison.diff)?;
+      (Some(actual_path), Some(expected_path), Some(diff_path))
+    };
+
+    Ok(ImageMatchResult {
+      matched,
+      updated: false,
+      dimensions_match,
+      total_pixels: comparison.total_pixels,
+      mismatched_pixels: comparison.mismatched_pixels,
+      mismatch_percent,
+      max_color_distance: comparison.max_distance / MAX_COLOR_DISTANCE,
+      mean_color_distance: if comparison.total_pixels == 0 {
+        0.0
+      } else {
+        comparison.total_distance / f64::from(comparison.total_pixels) / MAX_COLOR_DISTANCE
+      },
+      mismatch_bounds: comparison
+        .bounds
+        .map(|(min_x, min_y, max_x, max_y)| Region {
+          x: min_x,
+          y: min_y,
+          width: max_x - min_x + 1,
+          height: max_y - min_y + 1,
+        }),
+      actual_path,
+      expected_path,
+      diff_path,
+    })
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
+    Ok(output)
+  }
+}
+
+/// Compares an image against a golden image on disk. When they differ, the
+/// actual, expected and diff images are written out for inspection, by default
+/// to a temporary directory rather than beside the golden image. A missing
+/// golden image is an error unless updating.
+#[napi(ts_return_type = "Promise<ImageMatchResult>")]
+pub fn expect_image_match(
+  actual: &Image,
+  expected_path: String,
+  options: Option<ImageMatchOptions>,
+) -> AsyncTask<AsyncExpectImageMatch> {
+  AsyncTask::new(AsyncExpectImageMatch {
+    actual: actual.rgba_image.clone(),
+    expected_path: PathBuf::from(expected_path),
+    options: options.unwrap_or_default(),
+  })
+}
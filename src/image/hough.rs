@@ -0,0 +1,516 @@
+use image::{imageops, RgbaImage};
+use napi::{Env, Error, Task};
+use std::{cmp::Reverse, f64::consts::PI};
+
+use crate::image::{Cancellation, Point};
+
+const DEFAULT_EDGE_THRESHOLD: f64 = 0.25;
+const DEFAULT_ANGLE_STEP: f64 = 1.0;
+const DEFAULT_MIN_SUPPORT: f64 = 0.5;
+const LINE_SUPPRESSION_RADIUS: i64 = 2;
+/// Accumulator peaks considered as circle centres, strongest first.
+const MAX_CIRCLE_CANDIDATES: usize = 1000;
+/// Three points are the fewest that define a circle.
+const MIN_CIRCLE_VOTES: f64 = 3.0;
+
+#[napi(object)]
+#[derive(Clone, Copy, Default)]
+pub struct HoughLineOptions {
+  /// Gradient magnitude, as a fraction of the strongest gradient in the image,
+  /// above which a pixel counts as an edge. Defaults to 0.25.
+  pub edge_threshold: Option<f64>,
+  /// Angular resolution in degrees. Defaults to 1.
+  pub angle_step: Option<f64>,
+  /// Votes a line needs to be reported. Defaults to half the shorter side of
+  /// the image.
+  pub min_votes: Option<u32>,
+  /// Maximum number of lines returned, strongest first.
+  pub limit: Option<u32>,
+}
+
+#[napi(object)]
+#[derive(Clone, Copy, Default)]
+pub struct HoughCircleOptions {
+  /// Gradient magnitude, as a fraction of the strongest gradient in the image,
+  /// above which a pixel counts as an edge. Defaults to 0.25.
+  pub edge_threshold: Option<f64>,
+  /// Fraction of a circle's circumference that must lie on edges for it to be
+  /// reported. Defaults to 0.5.
+  pub min_support: Option<f64>,
+  /// Minimum distance between the centres of reported circles. Defaults to the
+  /// minimum radius.
+  pub min_distance: Option<u32>,
+  /// Maximum number of circles returned, strongest first.
+  pub limit: Option<u32>,
+}
+
+/// A line in normal form: the points `(x, y)` with
+/// `x * cos(angle) + y * sin(angle) = rho`.
+#[napi(object)]
+pub struct HoughLine {
+  pub rho: f64,
+  /// Angle of the line's normal in degrees, from 0 up to 180.
+  pub angle: f64,
+  pub votes: u32,
+  /// Where the line enters and leaves the image.
+  pub start: Point,
+  pub end: Point,
+}
+
+#[napi(object)]
+pub struct HoughCircle {
+  pub x: u32,
+  pub y: u32,
+  pub radius: u32,
+  /// Number of edge pixels on the circle.
+  pub votes: u32,
+}
+
+struct Edge {
+  x: u32,
+  y: u32,
+  gradient_x: f64,
+  gradient_y: f64,
+}
+
+/// Sobel edges thinned to the local maxima along the gradient direction, in
+/// row-major order.
+fn detect_edges(
+  rgba_image: &RgbaImage,
+  threshold: f64,
+  cancellation: &Cancellation,
+) -> Result<Vec<Edge>, Error> {
+  let luma = imageops::grayscale(rgba_image);
+  let (width, height) = luma.dimensions();
+  if width < 3 || height < 3 {
+    return Ok(Vec::new());
+  }
+
+  let at = |x: u32, y: u32| f64::from(luma.get_pixel(x, y).0[0]);
+  let mut gradients = vec![(0.0, 0.0, 0.0); (width * height) as usize];
+  let mut max_magnitude: f64 = 0.0;
+
+  for y in 1..height - 1 {
+    cancellation.check()?;
+
+    for x in 1..width - 1 {
+      let gradient_x = at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)
+        - at(x - 1, y - 1)
+        - 2.0 * at(x - 1, y)
+        - at(x - 1, y + 1);
+      let gradient_y = at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)
+        - at(x - 1, y - 1)
+        - 2.0 * at(x, y - 1)
+        - at(x + 1, y - 1);
+      let magnitude = gradient_x.hypot(gradient_y);
+      max_magnitude = max_magnitude.max(magnitude);
+      gradients[(y * width + x) as usize] = (gradient_x, gradient_y, magnitude);
+    }
+  }
+
+  let min_magnitude = max_magnitude * threshold;
+  let magnitude_at = |x: i64, y: i64| gradients[(y * i64::from(width) + x) as usize].2;
+  let mut edges = Vec::new();
+
+  for y in 1..height - 1 {
+    for x in 1..width - 1 {
+      let (gradient_x, gradient_y, magnitude) = gradients[(y * width + x) as usize];
+      if magnitude <= min_magnitude || magnitude == 0.0 {
+        continue;
+      }
+
+      // Compare against the two neighbours along the gradient, quantised to
+      // one of four directions.
+      let angle = gradient_y.atan2(gradient_x).to_degrees().rem_euclid(180.0);
+      let (dx, dy) = if !(22.5..157.5).contains(&angle) {
+        (1, 0)
+      } else if angle < 67.5 {
+        (1, 1)
+      } else if angle < 112.5 {
+        (0, 1)
+      } else {
+        (-1, 1)
+      };
+      let (x_i, y_i) = (i64::from(x), i64::from(y));
+      if magnitude < magnitude_at(x_i + dx, y_i + dy)
+        || magnitude < magnitude_at(x_i - dx, y_i - dy)
+      {
+        continue;
+      }
+
+      edges.push(Edge {
+        x,
+        y,
+        gradient_x,
+        gradient_y,
+      });
+    }
+  }
+
+  Ok(edges)
+}
+
+/// Where a line in normal form crosses the image bounds, if it does.
+fn clip_line(rho: f64, theta: f64, width: u32, height: u32) -> Option<(Point, Point)> {
+  let (sin, cos) = theta.sin_cos();
+  let origin = (rho * cos, rho * sin);
+  let direction = (-sin, cos);
+  let (max_x, max_y) = (f64::from(width) - 1.0, f64::from(height) - 1.0);
+
+  let mut t_min = f64::NEG_INFINITY;
+  let mut t_max = f64::INFINITY;
+  for (position, delta, max) in [
+    (origin.0, direction.0, max_x),
+    (origin.1, direction.1, max_y),
+  ] {
+    if delta.abs() < 1e-12 {
+      if position < 0.0 || position > max {
+        return None;
+      }
+      continue;
+    }
+    let (t0, t1) = ((0.0 - position) / delta, (max - position) / delta);
+    t_min = t_min.max(t0.min(t1));
+    t_max = t_max.min(t0.max(t1));
+  }
+
+  if t_min > t_max {
+    return None;
+  }
+
+  let point = |t: f64| Point {
+    x: origin.0 + direction.0 * t,
+    y: origin.1 + direction.1 * t,
+  };
+  Some((point(t_min), point(t_max)))
+}
+
+pub struct AsyncDetectLines {
+  rgba_image: RgbaImage,
+  options: HoughLineOptions,
+  cancellation: Cancellation,
+}
+
+impl AsyncDetectLines {
+  pub fn new(
+    rgba_image: RgbaImage,
+    options: Option<HoughLineOptions>,
+    cancellation: Cancellation,
+  ) -> Self {
+    Self {
+      rgba_image,
+      options: options.unwrap_or_default(),
+      cancellation,
+    }
+  }
+}
+
+#[napi]
+impl Task for AsyncDetectLines {
+  type Output = Vec<HoughLine>;
+  type JsValue = Vec<HoughLine>;
+
+  fn compute(&mut self) -> Result<Self::Output, Error> {
+    let (width, height) = self.rgba_image.dimensions();
+    let edges = detect_edges(
+      &self.rgba_image,
+      self
+        .options
+        .edge_threshold
+        .unwrap_or(DEFAULT_EDGE_THRESHOLD),
+      &self.cancellation,
+    )?;
+
+    let angle_step = self
+      .options
+      .angle_step
+      .unwrap_or(DEFAULT_ANGLE_STEP)
+      .clamp(0.01, 180.0);
+    let angle_count = (180.0 / angle_step).round().max(1.0) as usize;
+    let trig: Vec<(f64, f64)> = (0..angle_count)
+      .map(|i| (i as f64 * angle_step).to_radians().sin_cos())
+      .collect();
+    let max_rho = f64::from(width).hypot(f64::from(height)).ceil() as i64;
+    let rho_count = (2 * max_rho + 1) as usize;
+    let mut accumulator = vec![0u32; angle_count * rho_count];
+
+    for edge in &edges {
+      self.cancellation.check()?;
+
+      let (x, y) = (f64::from(edge.x), f64::from(edge.y));
+      for (angle_index, (sin, cos)) in trig.iter().enumerate() {
+        let rho = (x * cos + y * sin).round() as i64;
+        accumulator[angle_index * rho_count + (rho + max_rho) as usize] += 1;
+      }
+    }
+
+    let min_votes = self
+      .options
+      .min_votes
+      .unwrap_or(width.min(height) / 2)
+      .max(1);
+    let mut lines = Vec::new();
+
+    for angle_index in 0..angle_count {
+      for rho_index in 0..rho_count {
+        let votes = accumulator[angle_index * rho_count + rho_index];
+        if votes < min_votes {
+          continue;
+        }
+
+        let is_peak = (-LINE_SUPPRESSION_RADIUS..=LINE_SUPPRESSION_RADIUS).all(|d_angle| {
+          (-LINE_SUPPRESSION_RADIUS..=LINE_SUPPRESSION_RADIUS).all(|d_rho| {
+            if (d_angle, d_rho) == (0, 0) {
+              return true;
+            }
+            let mut other_angle = angle_index as i64 + d_angle;
+            let mut other_rho = rho_index as i64 + d_rho;
+            // Angles wrap around at 180 degrees with the sign of rho flipped.
+            if other_angle < 0 || other_angle >= angle_count as i64 {
+              other_angle = other_angle.rem_euclid(angle_count as i64);
+              other_rho = rho_count as i64 - 1 - other_rho;
+            }
+            if other_rho < 0 || other_rho >= rho_count as i64 {
+              return true;
+            }
+            let other = accumulator[other_angle as usize * rho_count + other_rho as usize];
+            // Break ties towards the earlier cell so plateaus yield one line.
+            other < votes || (other == votes && (d_angle, d_rho) > (0, 0))
+          })
+        });
+        if !is_peak {
+          continue;
+        }
+
+        let rho = rho_index as f64 - max_rho as f64;
+        let angle = angle_index as f64 * angle_step;
+        if let Some((start, end)) = clip_line(rho, angle.to_radians(), width, height) {
+          lines.push(HoughLine {
+            rho,
+            angle,
+            votes,
+            start,
+            end,
+          });
+        }
+      }
+    }
+
+    lines.sort_by_key(|line| Reverse(line.votes));
+    if let Some(limit) = self.options.limit {
+      lines.truncate(limit as usize);
+    }
+
+    Ok(lines)
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
+    Ok(output)
+  }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue, Error> {
+    self.cancellation.reject(&env, err)
+  }
+}
+
+pub struct AsyncDetectCircles {
+  rgba_image: RgbaImage,
+  min_radius: u32,
+  max_radius: u32,
+  options: HoughCircleOptions,
+  cancellation: Cancellation,
+}
+
+impl AsyncDetectCircles {
+  pub fn new(
+    rgba_image: RgbaImage,
+    min_radius: u32,
+    max_radius: u32,
+    options: Option<HoughCircleOptions>,
+    cancellation: Cancellation,
+  ) -> Result<Self, Error> {
+    if min_radius > max_radius {
+      return Err(Error::from_reason(
+        "The minimum radius must not be greater than the maximum radius",
+      ));
+    }
+
+    // No circle with its centre in the image and an edge pixel on it can be
+    // larger than the image's diagonal.
+    let (width, height) = rgba_image.dimensions();
+    let diagonal = f64::from(width).hypot(f64::from(height)).ceil() as u32;
+    let min_radius = min_radius.max(1);
+
+    Ok(Self {
+      rgba_image,
+      min_radius,
+      max_radius: max_radius.min(diagonal).max(min_radius),
+      options: options.unwrap_or_default(),
+      cancellation,
+    })
+  }
+
+  /// The radius around a centre with the most edge pixels, and that count.
+  /// Only edges within `max_radius` of the centre on both axes are visited.
+  fn best_radius(&self, edges: &[Edge], centre_x: u32, centre_y: u32) -> (u32, u32) {
+    let top = centre_y.saturating_sub(self.max_radius);
+    let bottom = centre_y.saturating_add(self.max_radius);
+    let start = edges.partition_point(|edge| edge.y < top);
+    let end = edges.partition_point(|edge| edge.y <= bottom);
+    let (left, right) = (
+      centre_x.saturating_sub(self.max_radius),
+      centre_x.saturating_add(self.max_radius),
+    );
+
+    let mut counts = vec![0u32; (self.max_radius - self.min_radius + 1) as usize];
+    for edge in edges[start..end]
+      .iter()
+      .filter(|edge| (left..=right).contains(&edge.x))
+    {
+      let distance = (f64::from(edge.x) - f64::from(centre_x))
+        .hypot(f64::from(edge.y) - f64::from(centre_y))
+        .round() as u32;
+      if (self.min_radius..=self.max_radius).contains(&distance) {
+        counts[(distance - self.min_radius) as usize] += 1;
+      }
+    }
+
+    counts
+      .iter()
+      .enumerate()
+      .map(|(i, &count)| (self.min_radius + i as u32, count))
+      .max_by(|a, b| {
+        // Compare by the fraction of the circumference covered so larger
+        // circles are not favoured just for having more pixels.
+        let support = |(radius, count): (u32, u32)| f64::from(count) / f64::from(radius);
+        support(*a).total_cmp(&support(*b))
+      })
+      .unwrap_or((self.min_radius, 0))
+  }
+}
+
+#[napi]
+impl Task for AsyncDetectCircles {
+  type Output = Vec<HoughCircle>;
+  type JsValue = Vec<HoughCircle>;
+
+  fn compute(&mut self) -> Result<Self::Output, Error> {
+    let (width, height) = self.rgba_image.dimensions();
+    let edges = detect_edges(
+      &self.rgba_image,
+      self
+        .options
+        .edge_threshold
+        .unwrap_or(DEFAULT_EDGE_THRESHOLD),
+      &self.cancellation,
+    )?;
+
+    // Every edge votes for the centres along its gradient, in both directions
+    // so that both light-on-dark and dark-on-light circles are found.
+    let mut accumulator = vec![0u32; (width * height) as usize];
+    for edge in &edges {
+      self.cancellation.check()?;
+
+      let magnitude = edge.gradient_x.hypot(edge.gradient_y);
+      let (dx, dy) = (edge.gradient_x / magnitude, edge.gradient_y / magnitude);
+      for sign in [-1.0, 1.0] {
+        for radius in self.min_radius..=self.max_radius {
+          let x = (f64::from(edge.x) + sign * dx * f64::from(radius)).round();
+          let y = (f64::from(edge.y) + sign * dy * f64::from(radius)).round();
+          if x >= 0.0 && y >= 0.0 && x < f64::from(width) && y < f64::from(height) {
+            accumulator[(y as u32 * width + x as u32) as usize] += 1;
+          }
+        }
+      }
+    }
+
+    // A centre gets about one vote per edge on its circle, but rounding spreads
+    // them over neighbouring cells, so half of what the smallest circle needs
+    // is enough to be a candidate.
+    let min_support = self.options.min_support.unwrap_or(DEFAULT_MIN_SUPPORT);
+    let min_circle_votes = |radius: u32| {
+      (2.0 * PI * f64::from(radius) * min_support)
+        .ceil()
+        .max(MIN_CIRCLE_VOTES)
+    };
+    let min_centre_votes = (min_circle_votes(self.min_radius) / 2.0).ceil() as u32;
+
+    // Only local maxima of the accumulator are candidates. Plateaus are kept
+    // whole, since rounding often leaves the true centre tied with its
+    // neighbours.
+    let votes_at = |x: i64, y: i64| {
+      if x < 0 || y < 0 || x >= i64::from(width) || y >= i64::from(height) {
+        0
+      } else {
+        accumulator[(y * i64::from(width) + x) as usize]
+      }
+    };
+    let mut candidates: Vec<(u32, u32, u32)> = Vec::new();
+    for y in 0..height {
+      self.cancellation.check()?;
+
+      for x in 0..width {
+        let votes = accumulator[(y * width + x) as usize];
+        if votes < min_centre_votes {
+          continue;
+        }
+        let is_peak = (-1..=1).all(|dy: i64| {
+          (-1..=1).all(|dx: i64| {
+            let other = votes_at(i64::from(x) + dx, i64::from(y) + dy);
+            other <= votes
+          })
+        });
+        if is_peak {
+          candidates.push((x, y, votes));
+        }
+      }
+    }
+    candidates.sort_by_key(|&(_, _, votes)| Reverse(votes));
+    candidates.truncate(MAX_CIRCLE_CANDIDATES);
+
+    let min_distance = f64::from(self.options.min_distance.unwrap_or(self.min_radius));
+    let limit = self.options.limit.map(|limit| limit as usize);
+    let mut supported: Vec<HoughCircle> = Vec::new();
+    for (x, y, _) in candidates {
+      self.cancellation.check()?;
+
+      let (radius, votes) = self.best_radius(&edges, x, y);
+      if f64::from(votes) >= min_circle_votes(radius) {
+        supported.push(HoughCircle {
+          x,
+          y,
+          radius,
+          votes,
+        });
+      }
+    }
+
+    // Among nearby candidates, keep the one with the most edges on its circle.
+    supported.sort_by_key(|circle| Reverse(circle.votes));
+    let mut circles: Vec<HoughCircle> = Vec::new();
+
+    for candidate in supported {
+      if limit.is_some_and(|limit| circles.len() >= limit) {
+        break;
+      }
+      let too_close = circles.iter().any(|circle| {
+        (f64::from(circle.x) - f64::from(candidate.x))
+          .hypot(f64::from(circle.y) - f64::from(candidate.y))
+          < min_distance
+      });
+      if !too_close {
+        circles.push(candidate);
+      }
+    }
+
+    Ok(circles)
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
+    Ok(output)
+  }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue, Error> {
+    self.cancellation.reject(&env, err)
+  }
+}
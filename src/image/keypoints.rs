@@ -0,0 +1,714 @@
+use image::{imageops, GrayImage, RgbaImage};
+use napi::{Env, Error, Task};
+use once_cell::sync::Lazy;
+use rand::{seq::index::sample, Rng, SeedableRng};
+
+use crate::image::{Cancellation, Point};
+
+const FAST_CIRCLE: [(i32, i32); 16] = [
+  (0, -3),
+  (1, -3),
+  (2, -2),
+  (3, -1),
+  (3, 0),
+  (3, 1),
+  (2, 2),
+  (1, 3),
+  (0, 3),
+  (-1, 3),
+  (-2, 2),
+  (-3, 1),
+  (-3, 0),
+  (-3, -1),
+  (-2, -2),
+  (-1, -3),
+];
+const FAST_ARC_LENGTH: usize = 9;
+const PATCH_RADIUS: i32 = 15;
+const BORDER: u32 = PATCH_RADIUS as u32 + 1;
+const DESCRIPTOR_BITS: usize = 256;
+
+const DEFAULT_FAST_THRESHOLD: u32 = 20;
+const DEFAULT_MAX_KEYPOINTS: u32 = 500;
+const DEFAULT_PYRAMID_LEVELS: u32 = 4;
+const DEFAULT_SCALE_FACTOR: f64 = 1.2;
+const DEFAULT_MAX_HAMMING_DISTANCE: u32 = 64;
+const DEFAULT_RATIO: f64 = 0.8;
+const DEFAULT_RANSAC_ITERATIONS: u32 = 1000;
+const DEFAULT_RANSAC_THRESHOLD: f64 = 3.0;
+
+type PointPair = ((f64, f64), (f64, f64));
+
+/// Point pairs compared by the binary descriptor, sampled uniformly inside the
+/// patch circle so that they stay within the patch whatever its orientation.
+static DESCRIPTOR_PATTERN: Lazy<Vec<PointPair>> = Lazy::new(|| {
+  let mut rng = rand::rngs::StdRng::seed_from_u64(0x4845_524F);
+  let mut sample_point = || loop {
+    let x = rng.random_range(-PATCH_RADIUS..=PATCH_RADIUS);
+    let y = rng.random_range(-PATCH_RADIUS..=PATCH_RADIUS);
+    if x * x + y * y <= (PATCH_RADIUS - 1) * (PATCH_RADIUS - 1) {
+      return (x as f64, y as f64);
+    }
+  };
+
+  (0..DESCRIPTOR_BITS)
+    .map(|_| (sample_point(), sample_point()))
+    .collect()
+});
+
+#[napi(string_enum)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransformModel {
+  Affine,
+  Homography,
+}
+
+#[napi(object)]
+#[derive(Clone, Copy, Default)]
+pub struct KeypointOptions {
+  pub fast_threshold: Option<u32>,
+  pub max_keypoints: Option<u32>,
+  pub pyramid_levels: Option<u32>,
+  pub scale_factor: Option<f64>,
+}
+
+#[napi(object)]
+#[derive(Clone, Copy, Default)]
+pub struct LocateImageOptions {
+  pub keypoints: Option<KeypointOptions>,
+  pub model: Option<TransformModel>,
+  pub max_hamming_distance: Option<u32>,
+  /// Lowe's ratio test: a match is kept only when its distance is below this
+  /// fraction of the distance to the second best candidate.
+  pub ratio: Option<f64>,
+  pub ransac_iterations: Option<u32>,
+  /// Maximum reprojection error, in pixels, for a match to count as an inlier.
+  pub ransac_threshold: Option<f64>,
+  pub min_inliers: Option<u32>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct Keypoint {
+  pub x: f64,
+  pub y: f64,
+  /// Orientation of the keypoint in radians.
+  pub angle: f64,
+  pub response: f64,
+  pub octave: u32,
+}
+
+#[napi(object)]
+pub struct ImageLocation {
+  /// Corners of the reference image in the searched image, clockwise from the
+  /// reference's top-left.
+  pub corners: Vec<Point>,
+  /// Row-major 3x3 matrix mapping reference coordinates into the searched image.
+  pub transform: Vec<f64>,
+  pub matches: u32,
+  pub inliers: u32,
+}
+
+struct DescribedKeypoint {
+  keypoint: Keypoint,
+  descriptor: [u64; DESCRIPTOR_BITS / 64],
+}
+
+struct KeypointSettings {
+  fast_threshold: u8,
+  max_keypoints: usize,
+  pyramid_levels: u32,
+  scale_factor: f64,
+}
+
+impl From<Option<KeypointOptions>> for KeypointSettings {
+  fn from(value: Option<KeypointOptions>) -> Self {
+    let options = value.unwrap_or_default();
+
+    Self {
+      fast_threshold: options
+        .fast_threshold
+        .unwrap_or(DEFAULT_FAST_THRESHOLD)
+        .clamp(1, 255) as u8,
+      max_keypoints: options.max_keypoints.unwrap_or(DEFAULT_MAX_KEYPOINTS) as usize,
+      pyramid_levels: options
+        .pyramid_levels
+        .unwrap_or(DEFAULT_PYRAMID_LEVELS)
+        .max(1),
+      scale_factor: options
+        .scale_factor
+        .filter(|factor| *factor > 1.0)
+        .unwrap_or(DEFAULT_SCALE_FACTOR),
+    }
+  }
+}
+
+fn fast_score(image: &GrayImage, x: u32, y: u32, threshold: u8) -> Option<f64> {
+  let centre = image.get_pixel(x, y).0[0] as i32;
+  let threshold = threshold as i32;
+  let ring = FAST_CIRCLE.map(|(dx, dy)| {
+    image
+      .get_pixel((x as i32 + dx) as u32, (y as i32 + dy) as u32)
+      .0[0] as i32
+  });
+
+  // Any arc of nine contiguous pixels covers at least two of the four compass
+  // points, which rejects most candidates cheaply.
+  let compass_extremes = [ring[0], ring[4], ring[8], ring[12]]
+    .iter()
+    .filter(|&&value| (value - centre).abs() > threshold)
+    .count();
+  if compass_extremes < 2 {
+    return None;
+  }
+
+  let has_arc = |is_extreme: &dyn Fn(i32) -> bool| {
+    let mut run = 0;
+    for i in 0..(FAST_CIRCLE.len() + FAST_ARC_LENGTH) {
+      if is_extreme(ring[i % FAST_CIRCLE.len()]) {
+        run += 1;
+        if run >= FAST_ARC_LENGTH {
+          return true;
+        }
+      } else {
+        run = 0;
+      }
+    }
+    false
+  };
+
+  let brighter = has_arc(&|value| value > centre + threshold);
+  let darker = !brighter && has_arc(&|value| value < centre - threshold);
+
+  if !brighter && !darker {
+    return None;
+  }
+
+  let score = ring
+    .iter()
+    .map(|value| ((value - centre).abs() - threshold).max(0))
+    .sum::<i32>();
+
+  Some(score as f64)
+}
+
+fn detect_fast_corners(image: &GrayImage, threshold: u8) -> Vec<(u32, u32, f64)> {
+  let (width, height) = image.dimensions();
+  if width <= BORDER * 2 || height <= BORDER * 2 {
+    return Vec::new();
+  }
+
+  let mut scores = vec![0.0; (width * height) as usize];
+  for y in BORDER..(height - BORDER) {
+    for x in BORDER..(width - BORDER) {
+      if let Some(score) = fast_score(image, x, y, threshold) {
+        scores[(y * width + x) as usize] = score;
+      }
+    }
+  }
+
+  let mut corners = Vec::new();
+  for y in BORDER..(height - BORDER) {
+    for x in BORDER..(width - BORDER) {
+      let score = scores[(y * width + x) as usize];
+      if score <= 0.0 {
+        continue;
+      }
+
+      let is_maximum = (-1i32..=1).all(|dy| {
+        (-1i32..=1).all(|dx| {
+          let neighbour = (((y as i32 + dy) as u32) * width + (x as i32 + dx) as u32) as usize;
+          (dx == 0 && dy == 0) || scores[neighbour] < score
+        })
+      });
+
+      if is_maximum {
+        corners.push((x, y, score));
+      }
+    }
+  }
+
+  corners
+}
+
+/// Orientation from the intensity centroid of the circular patch around the
+/// keypoint, as used by ORB.
+fn patch_orientation(image: &GrayImage, x: u32, y: u32) -> f64 {
+  let mut m01 = 0.0;
+  let mut m10 = 0.0;
+
+  for dy in -PATCH_RADIUS..=PATCH_RADIUS {
+    for dx in -PATCH_RADIUS..=PATCH_RADIUS {
+      if dx * dx + dy * dy > PATCH_RADIUS * PATCH_RADIUS {
+        continue;
+      }
+
+      let intensity = image
+        .get_pixel((x as i32 + dx) as u32, (y as i32 + dy) as u32)
+        .0[0] as f64;
+      m10 += dx as f64 * intensity;
+      m01 += dy as f64 * intensity;
+    }
+  }
+
+  m01.atan2(m10)
+}
+
+fn describe(image: &GrayImage, x: u32, y: u32, angle: f64) -> [u64; DESCRIPTOR_BITS / 64] {
+  let (sin, cos) = angle.sin_cos();
+  let intensity_at = |(px, py): (f64, f64)| {
+    let rotated_x = (px * cos - py * sin).round() as i32;
+    let rotated_y = (px * sin + py * cos).round() as i32;
+    image
+      .get_pixel((x as i32 + rotated_x) as u32, (y as i32 + rotated_y) as u32)
+      .0[0]
+  };
+
+  let mut descriptor = [0u64; DESCRIPTOR_BITS / 64];
+  for (bit, (first, second)) in DESCRIPTOR_PATTERN.iter().enumerate() {
+    if intensity_at(*first) < intensity_at(*second) {
+      descriptor[bit / 64] |= 1 << (bit % 64);
+    }
+  }
+
+  descriptor
+}
+
+fn hamming_distance(a: &[u64; DESCRIPTOR_BITS / 64], b: &[u64; DESCRIPTOR_BITS / 64]) -> u32 {
+  a.iter()
+    .zip(b.iter())
+    .map(|(a, b)| (a ^ b).count_ones())
+    .sum()
+}
+
+fn detect_and_describe(
+  rgba_image: &RgbaImage,
+  settings: &KeypointSettings,
+  cancellation: &Cancellation,
+) -> Result<Vec<DescribedKeypoint>, Error> {
+  let gray = imageops::grayscale(rgba_image);
+  let mut keypoints = Vec::new();
+  let mut level_image = gray;
+  let mut scale = 1.0;
+
+  for octave in 0..settings.pyramid_levels {
+    cancellation.check()?;
+
+    if octave > 0 {
+      scale *= settings.scale_factor;
+      let width = (rgba_image.width() as f64 / scale).round() as u32;
+      let height = (rgba_image.height() as f64 / scale).round() as u32;
+      if width <= BORDER * 2 || height <= BORDER * 2 {
+        break;
+      }
+      level_image = imageops::resize(&level_image, width, height, imageops::FilterType::Triangle);
+    }
+
+    let smoothed = imageops::blur(&level_image, 2.0);
+
+    for (x, y, response) in detect_fast_corners(&level_image, settings.fast_threshold) {
+      let angle = patch_orientation(&level_image, x, y);
+
+      keypoints.push(DescribedKeypoint {
+        keypoint: Keypoint {
+          x: x as f64 * scale,
+          y: y as f64 * scale,
+          angle,
+          response,
+          octave,
+        },
+        descriptor: describe(&smoothed, x, y, angle),
+      });
+    }
+  }
+
+  keypoints.sort_by(|a, b| b.keypoint.response.total_cmp(&a.keypoint.response));
+  keypoints.truncate(settings.max_keypoints);
+
+  Ok(keypoints)
+}
+
+/// Solves `a * x = b` with Gaussian elimination and partial pivoting.
+fn solve_linear_system(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+  let n = b.len();
+
+  for column in 0..n {
+    let pivot = (column..n).max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))?;
+    if a[pivot][column].abs() < 1e-12 {
+      return None;
+    }
+    a.swap(column, pivot);
+    b.swap(column, pivot);
+
+    let (pivot_rows, remaining_rows) = a.split_at_mut(column + 1);
+    let pivot_row = &pivot_rows[column];
+    for (offset, row) in remaining_rows.iter_mut().enumerate() {
+      let factor = row[column] / pivot_row[column];
+      for (value, pivot_value) in row.iter_mut().zip(pivot_row.iter()).skip(column) {
+        *value -= factor * pivot_value;
+      }
+      b[column + 1 + offset] -= factor * b[column];
+    }
+  }
+
+  let mut x = vec![0.0; n];
+  for row in (0..n).rev() {
+    let sum: f64 = ((row + 1)..n).map(|k| a[row][k] * x[k]).sum();
+    x[row] = (b[row] - sum) / a[row][row];
+  }
+
+  Some(x)
+}
+
+/// Similarity transform moving the points' centroid to the origin with an
+/// average distance of sqrt(2), which keeps the normal equations well
+/// conditioned.
+fn normalisation(points: &[(f64, f64)]) -> [f64; 9] {
+  let count = points.len() as f64;
+  let centre_x = points.iter().map(|p| p.0).sum::<f64>() / count;
+  let centre_y = points.iter().map(|p| p.1).sum::<f64>() / count;
+  let mean_distance = points
+    .iter()
+    .map(|p| ((p.0 - centre_x).powi(2) + (p.1 - centre_y).powi(2)).sqrt())
+    .sum::<f64>()
+    / count;
+  let scale = if mean_distance > 0.0 {
+    std::f64::consts::SQRT_2 / mean_distance
+  } else {
+    1.0
+  };
+
+  [
+    scale,
+    0.0,
+    -scale * centre_x,
+    0.0,
+    scale,
+    -scale * centre_y,
+    0.0,
+    0.0,
+    1.0,
+  ]
+}
+
+fn multiply(a: &[f64; 9], b: &[f64; 9]) -> [f64; 9] {
+  let mut result = [0.0; 9];
+  for row in 0..3 {
+    for column in 0..3 {
+      result[row * 3 + column] = (0..3).map(|k| a[row * 3 + k] * b[k * 3 + column]).sum();
+    }
+  }
+  result
+}
+
+fn invert_normalisation(t: &[f64; 9]) -> [f64; 9] {
+  let scale = t[0];
+  [
+    1.0 / scale,
+    0.0,
+    -t[2] / scale,
+    0.0,
+    1.0 / scale,
+    -t[5] / scale,
+    0.0,
+    0.0,
+    1.0,
+  ]
+}
+
+pub fn project(transform: &[f64; 9], x: f64, y: f64) -> (f64, f64) {
+  let w = transform[6] * x + transform[7] * y + transform[8];
+  (
+    (transform[0] * x + transform[1] * y + transform[2]) / w,
+    (transform[3] * x + transform[4] * y + transform[5]) / w,
+  )
+}
+
+/// Least-squares fit of the model to the correspondences, or `None` when they
+/// are degenerate.
+fn fit_transform(
+  model: TransformModel,
+  source: &[(f64, f64)],
+  destination: &[(f64, f64)],
+) -> Option<[f64; 9]> {
+  let source_normalisation = normalisation(source);
+  let destination_normalisation = normalisation(destination);
+  let unknowns = match model {
+    TransformModel::Affine => 6,
+    TransformModel::Homography => 8,
+  };
+
+  let mut normal_matrix = vec![vec![0.0; unknowns]; unknowns];
+  let mut normal_vector = vec![0.0; unknowns];
+
+  for (&(sx, sy), &(dx, dy)) in source.iter().zip(destination.iter()) {
+    let (x, y) = project(&source_normalisation, sx, sy);
+    let (u, v) = project(&destination_normalisation, dx, dy);
+
+    let rows: [(Vec<f64>, f64); 2] = match model {
+      TransformModel::Affine => [
+        (vec![x, y, 1.0, 0.0, 0.0, 0.0], u),
+        (vec![0.0, 0.0, 0.0, x, y, 1.0], v),
+      ],
+      TransformModel::Homography => [
+        (vec![x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y], u),
+        (vec![0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y], v),
+      ],
+    };
+
+    for (row, target) in rows.iter() {
+      for i in 0..unknowns {
+        for j in 0..unknowns {
+          normal_matrix[i][j] += row[i] * row[j];
+        }
+        normal_vector[i] += row[i] * target;
+      }
+    }
+  }
+
+  let solution = solve_linear_system(normal_matrix, normal_vector)?;
+  let normalised = match model {
+    TransformModel::Affine => [
+      solution[0],
+      solution[1],
+      solution[2],
+      solution[3],
+      solution[4],
+      solution[5],
+      0.0,
+      0.0,
+      1.0,
+    ],
+    TransformModel::Homography => [
+      solution[0],
+      solution[1],
+      solution[2],
+      solution[3],
+      solution[4],
+      solution[5],
+      solution[6],
+      solution[7],
+      1.0,
+    ],
+  };
+
+  let transform = multiply(
+    &invert_normalisation(&destination_normalisation),
+    &multiply(&normalised, &source_normalisation),
+  );
+  // Undoing the normalisation scales the matrix; the usual form has h33 = 1.
+  let transform = transform.map(|value| value / transform[8]);
+
+  transform
+    .iter()
+    .all(|value| value.is_finite())
+    .then_some(transform)
+}
+
+fn count_inliers(
+  transform: &[f64; 9],
+  source: &[(f64, f64)],
+  destination: &[(f64, f64)],
+  threshold: f64,
+) -> Vec<usize> {
+  source
+    .iter()
+    .zip(destination.iter())
+    .enumerate()
+    .filter(|(_, (&(sx, sy), &(dx, dy)))| {
+      let (px, py) = project(transform, sx, sy);
+      ((px - dx).powi(2) + (py - dy).powi(2)).sqrt() <= threshold
+    })
+    .map(|(index, _)| index)
+    .collect()
+}
+
+pub struct AsyncDetectKeypoints {
+  rgba_image: RgbaImage,
+  settings: KeypointSettings,
+  cancellation: Cancellation,
+}
+
+impl AsyncDetectKeypoints {
+  pub fn new(
+    rgba_image: RgbaImage,
+    options: Option<KeypointOptions>,
+    cancellation: Cancellation,
+  ) -> Self {
+    Self {
+      rgba_image,
+      settings: options.into(),
+      cancellation,
+    }
+  }
+}
+
+#[napi]
+impl Task for AsyncDetectKeypoints {
+  type Output = Vec<Keypoint>;
+  type JsValue = Vec<Keypoint>;
+
+  fn compute(&mut self) -> Result<Self::Output, Error> {
+    Ok(
+      detect_and_describe(&self.rgba_image, &self.settings, &self.cancellation)?
+        .into_iter()
+        .map(|described| described.keypoint)
+        .collect(),
+    )
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
+    Ok(output)
+  }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue, Error> {
+    self.cancellation.reject(&env, err)
+  }
+}
+
+pub struct AsyncLocateImage {
+  reference: RgbaImage,
+  rgba_image: RgbaImage,
+  options: LocateImageOptions,
+  cancellation: Cancellation,
+}
+
+impl AsyncLocateImage {
+  pub fn new(
+    reference: RgbaImage,
+    rgba_image: RgbaImage,
+    options: Option<LocateImageOptions>,
+    cancellation: Cancellation,
+  ) -> Self {
+    Self {
+      reference,
+      rgba_image,
+      options: options.unwrap_or_default(),
+      cancellation,
+    }
+  }
+}
+
+#[napi]
+impl Task for AsyncLocateImage {
+  type Output = Option<ImageLocation>;
+  type JsValue = Option<ImageLocation>;
+
+  fn compute(&mut self) -> Result<Self::Output, Error> {
+    let settings = KeypointSettings::from(self.options.keypoints);
+    let model = self.options.model.unwrap_or(TransformModel::Homography);
+    let max_distance = self
+      .options
+      .max_hamming_distance
+      .unwrap_or(DEFAULT_MAX_HAMMING_DISTANCE);
+    let ratio = self.options.ratio.unwrap_or(DEFAULT_RATIO);
+    let iterations = self
+      .options
+      .ransac_iterations
+      .unwrap_or(DEFAULT_RANSAC_ITERATIONS);
+    let threshold = self
+      .options
+      .ransac_threshold
+      .unwrap_or(DEFAULT_RANSAC_THRESHOLD);
+    let sample_size = match model {
+      TransformModel::Affine => 3,
+      TransformModel::Homography => 4,
+    };
+    let min_inliers = (self.options.min_inliers.unwrap_or(8) as usize).max(sample_size);
+
+    let reference_keypoints = detect_and_describe(&self.reference, &settings, &self.cancellation)?;
+    let image_keypoints = detect_and_describe(&self.rgba_image, &settings, &self.cancellation)?;
+
+    let mut source = Vec::new();
+    let mut destination = Vec::new();
+
+    for reference_keypoint in &reference_keypoints {
+      let mut best: Option<(u32, &DescribedKeypoint)> = None;
+      let mut second_best_distance = u32::MAX;
+
+      for image_keypoint in &image_keypoints {
+        let distance = hamming_distance(&reference_keypoint.descriptor, &image_keypoint.descriptor);
+        match best {
+          Some((best_distance, _)) if distance >= best_distance => {
+            second_best_distance = second_best_distance.min(distance);
+          }
+          _ => {
+            if let Some((best_distance, _)) = best {
+              second_best_distance = best_distance;
+            }
+            best = Some((distance, image_keypoint));
+          }
+        }
+      }
+
+      if let Some((distance, image_keypoint)) = best {
+        let passes_ratio = (distance as f64) < ratio * second_best_distance as f64;
+        if distance <= max_distance && passes_ratio {
+          source.push((reference_keypoint.keypoint.x, reference_keypoint.keypoint.y));
+          destination.push((image_keypoint.keypoint.x, image_keypoint.keypoint.y));
+        }
+      }
+    }
+
+    if source.len() < min_inliers {
+      return Ok(None);
+    }
+
+    let mut rng = rand::rng();
+    let mut best_inliers: Vec<usize> = Vec::new();
+
+    for iteration in 0..iterations {
+      if iteration % 64 == 0 {
+        self.cancellation.check()?;
+      }
+
+      let indices = sample(&mut rng, source.len(), sample_size);
+      let sample_source: Vec<(f64, f64)> = indices.iter().map(|i| source[i]).collect();
+      let sample_destination: Vec<(f64, f64)> = indices.iter().map(|i| destination[i]).collect();
+
+      if let Some(transform) = fit_transform(model, &sample_source, &sample_destination) {
+        let inliers = count_inliers(&transform, &source, &destination, threshold);
+        if inliers.len() > best_inliers.len() {
+          best_inliers = inliers;
+        }
+      }
+    }
+
+    if best_inliers.len() < min_inliers {
+      return Ok(None);
+    }
+
+    let inlier_source: Vec<(f64, f64)> = best_inliers.iter().map(|&i| source[i]).collect();
+    let inlier_destination: Vec<(f64, f64)> =
+      best_inliers.iter().map(|&i| destination[i]).collect();
+    let transform = match fit_transform(model, &inlier_source, &inlier_destination) {
+      Some(transform) => transform,
+      None => return Ok(None),
+    };
+
+    let width = self.reference.width() as f64;
+    let height = self.reference.height() as f64;
+    let corners = [(0.0, 0.0), (width, 0.0), (width, height), (0.0, height)]
+      .iter()
+      .map(|&(x, y)| {
+        let (x, y) = project(&transform, x, y);
+        Point { x, y }
+      })
+      .collect();
+
+    Ok(Some(ImageLocation {
+      corners,
+      transform: transform.to_vec(),
+      matches: source.len() as u32,
+      inliers: best_inliers.len() as u32,
+    }))
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
+    Ok(output)
+  }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue, Error> {
+    self.cancellation.reject(&env, err)
+  }
+}
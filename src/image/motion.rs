@@ -0,0 +1,318 @@
+use image::RgbaImage;
+use napi::{
+  bindgen_prelude::{AbortSignal, AsyncTask},
+  Env, Error, Task,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+
+use crate::image::{Cancellation, Image};
+
+const MAX_COLOR_DISTANCE: f64 = 510.0; // Using alpha: sqrt(255^2 * 4)
+const DEFAULT_LEARNING_RATE: f64 = 0.05;
+const DEFAULT_THRESHOLD: f64 = 0.1;
+const DEFAULT_GROUPING_DISTANCE: u32 = 3;
+const DEFAULT_MIN_PIXELS: u32 = 4;
+const MAX_GROUPING_DISTANCE: u32 = 64;
+/// Above this fraction of moving pixels, such as when the scene changes, the
+/// whole frame is reported as a single region instead of grouping blobs.
+const MAX_MOVING_FRACTION: f64 = 0.5;
+
+#[napi(object)]
+#[derive(Clone, Copy, Default)]
+pub struct MotionOptions {
+  /// How quickly the background absorbs new frames, between 0 and 1. Defaults
+  /// to 0.05.
+  pub learning_rate: Option<f64>,
+  /// Colour distance from the background, as a fraction of the maximum, above
+  /// which a pixel counts as moving. Defaults to 0.1.
+  pub threshold: Option<f64>,
+  /// Maximum distance between moving pixels of the same blob, up to 64.
+  /// Defaults to 3.
+  pub grouping_distance: Option<u32>,
+  /// Blobs with fewer moving pixels than this are ignored as noise. Defaults
+  /// to 4.
+  pub min_pixels: Option<u32>,
+}
+
+#[napi(object)]
+pub struct MotionRegion {
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+  /// Number of moving pixels within the bounding box.
+  pub pixel_count: u32,
+}
+
+struct MotionState {
+  learning_rate: f64,
+  threshold: f64,
+  grouping_distance: u32,
+  min_pixels: u32,
+  width: u32,
+  height: u32,
+  /// Running average of every channel of every pixel, in image layout.
+  background: Vec<f64>,
+  /// Where an update writes the next background, swapped in once it completes
+  /// so that a cancelled update leaves the model untouched.
+  next_background: Vec<f64>,
+}
+
+impl MotionState {
+  fn update(
+    &mut self,
+    rgba_image: &RgbaImage,
+    cancellation: &Cancellation,
+  ) -> Result<Vec<MotionRegion>, Error> {
+    if self.background.is_empty()
+      || self.width != rgba_image.width()
+      || self.height != rgba_image.height()
+    {
+      self.width = rgba_image.width();
+      self.height = rgba_image.height();
+      self.background = rgba_image.as_raw().iter().map(|&c| f64::from(c)).collect();
+      return Ok(Vec::new());
+    }
+
+    let max_distance = MAX_COLOR_DISTANCE * self.threshold;
+    self.next_background.resize(self.background.len(), 0.0);
+    let mut moving = vec![false; (self.width as usize) * (self.height as usize)];
+    let mut whole_frame = Bounds::default();
+
+    for (i, (x, y, pixel)) in rgba_image.enumerate_pixels().enumerate() {
+      if x == 0 {
+        cancellation.check()?;
+      }
+
+      let mut distance_sq = 0.0;
+      for ((channel, average), next_average) in pixel
+        .0
+        .iter()
+        .zip(&self.background[i * 4..i * 4 + 4])
+        .zip(&mut self.next_background[i * 4..i * 4 + 4])
+      {
+        let channel = f64::from(*channel);
+        distance_sq += (channel - average).powi(2);
+        *next_average = average + (channel - average) * self.learning_rate;
+      }
+
+      if distance_sq.sqrt() > max_distance {
+        moving[i] = true;
+        whole_frame.add(x, y);
+      }
+    }
+
+    let regions = if f64::from(whole_frame.pixel_count) > MAX_MOVING_FRACTION * moving.len() as f64
+    {
+      vec![whole_frame.into()]
+    } else {
+      self
+        .group_moving_pixels(&moving, cancellation)?
+        .into_iter()
+        .filter(|bounds| bounds.pixel_count >= self.min_pixels)
+        .map(MotionRegion::from)
+        .collect()
+    };
+
+    std::mem::swap(&mut self.background, &mut self.next_background);
+    Ok(regions)
+  }
+
+  /// Groups moving pixels within `grouping_distance` of each other,
+  /// transitively, ordered by the scan position of each group's first pixel.
+  /// Each pixel is only compared with the mask around it.
+  fn group_moving_pixels(
+    &self,
+    moving: &[bool],
+    cancellation: &Cancellation,
+  ) -> Result<Vec<Bounds>, Error> {
+    let (width, height) = (i64::from(self.width), i64::from(self.height));
+    let distance = i64::from(self.grouping_distance);
+    // Pairs are found from their earlier pixel, so only later offsets count.
+    let offsets: Vec<(i64, i64)> = (0..=distance)
+      .flat_map(|dy| (-distance..=distance).map(move |dx| (dx, dy)))
+      .filter(|&(dx, dy)| (dy > 0 || dx > 0) && dx * dx + dy * dy <= distance * distance)
+      .collect();
+
+    let mut parent: Vec<usize> = (0..moving.len()).collect();
+    let find_set = |parent: &mut Vec<usize>, mut i: usize| {
+      while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+      }
+      i
+    };
+
+    for y in 0..height {
+      cancellation.check()?;
+
+      for x in 0..width {
+        let i = (y * width + x) as usize;
+        if !moving[i] {
+          continue;
+        }
+
+        for &(dx, dy) in &offsets {
+          let (nx, ny) = (x + dx, y + dy);
+          if nx < 0 || nx >= width || ny >= height {
+            continue;
+          }
+          let j = (ny * width + nx) as usize;
+          if moving[j] {
+            let (i_root, j_root) = (find_set(&mut parent, i), find_set(&mut parent, j));
+            if i_root != j_root {
+              parent[j_root] = i_root;
+            }
+          }
+        }
+      }
+    }
+
+    let mut group_indices: HashMap<usize, usize> = HashMap::new();
+    let mut groups: Vec<Bounds> = Vec::new();
+    for (i, _) in moving.iter().enumerate().filter(|(_, &moving)| moving) {
+      let root = find_set(&mut parent, i);
+      let group = *group_indices.entry(root).or_insert_with(|| {
+        groups.push(Bounds::default());
+        groups.len() - 1
+      });
+      groups[group].add((i as i64 % width) as u32, (i as i64 / width) as u32);
+    }
+
+    Ok(groups)
+  }
+}
+
+/// The bounding box of a set of moving pixels.
+#[derive(Default)]
+struct Bounds {
+  min_x: u32,
+  min_y: u32,
+  max_x: u32,
+  max_y: u32,
+  pixel_count: u32,
+}
+
+impl Bounds {
+  fn add(&mut self, x: u32, y: u32) {
+    if self.pixel_count == 0 {
+      (self.min_x, self.min_y, self.max_x, self.max_y) = (x, y, x, y);
+    } else {
+      self.min_x = self.min_x.min(x);
+      self.min_y = self.min_y.min(y);
+      self.max_x = self.max_x.max(x);
+      self.max_y = self.max_y.max(y);
+    }
+    self.pixel_count += 1;
+  }
+}
+
+impl From<Bounds> for MotionRegion {
+  fn from(bounds: Bounds) -> Self {
+    MotionRegion {
+      x: bounds.min_x,
+      y: bounds.min_y,
+      width: bounds.max_x - bounds.min_x + 1,
+      height: bounds.max_y - bounds.min_y + 1,
+      pixel_count: bounds.pixel_count,
+    }
+  }
+}
+
+fn lock_state(state: &Mutex<MotionState>) -> Result<MutexGuard<'_, MotionState>, Error> {
+  state.try_lock().map_err(|e| match e {
+    TryLockError::WouldBlock => Error::from_reason("Only one motion update is allowed at a time"),
+    TryLockError::Poisoned(_) => {
+      Error::from_reason("The motion detector can't be used after an update panicked")
+    }
+  })
+}
+
+pub struct AsyncMotionUpdate {
+  state: Arc<Mutex<MotionState>>,
+  rgba_image: RgbaImage,
+  cancellation: Cancellation,
+}
+
+#[napi]
+impl Task for AsyncMotionUpdate {
+  type Output = Vec<MotionRegion>;
+  type JsValue = Vec<MotionRegion>;
+
+  fn compute(&mut self) -> Result<Self::Output, Error> {
+    lock_state(&self.state)?.update(&self.rgba_image, &self.cancellation)
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
+    Ok(output)
+  }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue, Error> {
+    self.cancellation.reject(&env, err)
+  }
+}
+
+/// Keeps a running background model of successive captures and reports the
+/// regions that differ from it.
+#[napi]
+pub struct MotionDetector {
+  state: Arc<Mutex<MotionState>>,
+}
+
+#[napi]
+impl MotionDetector {
+  #[napi(constructor)]
+  pub fn new(options: Option<MotionOptions>) -> Result<Self, Error> {
+    let options = options.unwrap_or_default();
+    let grouping_distance = options
+      .grouping_distance
+      .unwrap_or(DEFAULT_GROUPING_DISTANCE);
+    if grouping_distance > MAX_GROUPING_DISTANCE {
+      return Err(Error::from_reason(format!(
+        "The grouping distance must be at most {MAX_GROUPING_DISTANCE}"
+      )));
+    }
+
+    Ok(MotionDetector {
+      state: Arc::new(Mutex::new(MotionState {
+        learning_rate: options
+          .learning_rate
+          .unwrap_or(DEFAULT_LEARNING_RATE)
+          .clamp(0.0, 1.0),
+        threshold: options.threshold.unwrap_or(DEFAULT_THRESHOLD),
+        grouping_distance,
+        min_pixels: options.min_pixels.unwrap_or(DEFAULT_MIN_PIXELS),
+        width: 0,
+        height: 0,
+        background: Vec::new(),
+        next_background: Vec::new(),
+      })),
+    })
+  }
+
+  /// Compares a frame against the background and folds it into the model. The
+  /// first frame, or one with different dimensions, only seeds the background
+  /// and reports no motion. When most of the frame moves, it is reported as a
+  /// single region.
+  #[napi(ts_return_type = "Promise<Array<MotionRegion>>")]
+  pub fn update(&self, image: &Image, signal: Option<AbortSignal>) -> AsyncTask<AsyncMotionUpdate> {
+    let cancellation = Cancellation::from_signal(signal.as_ref());
+    AsyncTask::with_optional_signal(
+      AsyncMotionUpdate {
+        state: self.state.clone(),
+        rgba_image: image.rgba_image.clone(),
+        cancellation,
+      },
+      signal,
+    )
+  }
+
+  /// Discards the background model so the next frame seeds a new one.
+  #[napi]
+  pub fn reset(&self) -> Result<(), Error> {
+    let mut state = lock_state(&self.state)?;
+    state.background.clear();
+    Ok(())
+  }
+}
@@ -0,0 +1,183 @@
+use image::RgbaImage;
+use napi::{Env, Error, Task};
+
+use crate::image::{Cancellation, Region};
+
+const HISTOGRAM_BINS: usize = 256;
+
+#[napi(object)]
+pub struct ChannelStatistics {
+  pub mean: f64,
+  pub stddev: f64,
+  pub min: u32,
+  pub max: u32,
+  /// Number of pixels with each channel value, indexed by value.
+  pub histogram: Vec<u32>,
+}
+
+#[napi(object)]
+pub struct RegionStatistics {
+  pub pixel_count: u32,
+  pub red: ChannelStatistics,
+  pub green: ChannelStatistics,
+  pub blue: ChannelStatistics,
+  pub alpha: ChannelStatistics,
+}
+
+#[napi(string_enum)]
+#[derive(Clone, Copy, Debug)]
+pub enum HistogramComparison {
+  /// Symmetric chi-square distance. 0 for identical histograms.
+  ChiSquare,
+  /// Shared proportion of the histograms. 1 for identical histograms.
+  Intersection,
+  /// Bhattacharyya distance. 0 for identical histograms, 1 for disjoint ones.
+  Bhattacharyya,
+}
+
+struct ChannelAccumulator {
+  sum: f64,
+  sum_of_squares: f64,
+  histogram: Vec<u32>,
+}
+
+impl ChannelAccumulator {
+  fn new() -> Self {
+    Self {
+      sum: 0.0,
+      sum_of_squares: 0.0,
+      histogram: vec![0; HISTOGRAM_BINS],
+    }
+  }
+
+  fn add(&mut self, value: u8) {
+    let value_f = f64::from(value);
+    self.sum += value_f;
+    self.sum_of_squares += value_f * value_f;
+    self.histogram[value as usize] += 1;
+  }
+
+  fn finish(self, pixel_count: u32) -> ChannelStatistics {
+    let count = f64::from(pixel_count.max(1));
+    let mean = self.sum / count;
+    let variance = (self.sum_of_squares / count - mean * mean).max(0.0);
+
+    ChannelStatistics {
+      mean,
+      stddev: variance.sqrt(),
+      min: self
+        .histogram
+        .iter()
+        .position(|&count| count > 0)
+        .unwrap_or(0) as u32,
+      max: self
+        .histogram
+        .iter()
+        .rposition(|&count| count > 0)
+        .unwrap_or(0) as u32,
+      histogram: self.histogram,
+    }
+  }
+}
+
+pub struct AsyncGetRegionStatistics {
+  rgba_image: RgbaImage,
+  region: Region,
+  cancellation: Cancellation,
+}
+
+impl AsyncGetRegionStatistics {
+  pub fn new(rgba_image: RgbaImage, region: Option<Region>, cancellation: Cancellation) -> Self {
+    Self {
+      region: Region::within(region, &rgba_image),
+      rgba_image,
+      cancellation,
+    }
+  }
+}
+
+#[napi]
+impl Task for AsyncGetRegionStatistics {
+  type Output = RegionStatistics;
+  type JsValue = RegionStatistics;
+
+  fn compute(&mut self) -> Result<Self::Output, Error> {
+    let mut channels: [ChannelAccumulator; 4] = std::array::from_fn(|_| ChannelAccumulator::new());
+
+    for y in self.region.y..self.region.y + self.region.height {
+      self.cancellation.check()?;
+
+      for x in self.region.x..self.region.x + self.region.width {
+        let pixel = self.rgba_image.get_pixel(x, y);
+        for (channel, value) in channels.iter_mut().zip(pixel.0) {
+          channel.add(value);
+        }
+      }
+    }
+
+    let pixel_count = self.region.width * self.region.height;
+    let [red, green, blue, alpha] = channels.map(|channel| channel.finish(pixel_count));
+
+    Ok(RegionStatistics {
+      pixel_count,
+      red,
+      green,
+      blue,
+      alpha,
+    })
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
+    Ok(output)
+  }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue, Error> {
+    self.cancellation.reject(&env, err)
+  }
+}
+
+fn normalise(histogram: &[u32]) -> Vec<f64> {
+  let total: f64 = histogram.iter().map(|&count| f64::from(count)).sum();
+  if total == 0.0 {
+    return vec![0.0; histogram.len()];
+  }
+  histogram
+    .iter()
+    .map(|&count| f64::from(count) / total)
+    .collect()
+}
+
+/// Compares two histograms of the same length after normalising each to sum to
+/// one, so histograms of differently sized regions can be compared.
+#[napi]
+pub fn compare_histograms(
+  first: Vec<u32>,
+  second: Vec<u32>,
+  method: HistogramComparison,
+) -> Result<f64, Error> {
+  if first.len() != second.len() {
+    return Err(Error::from_reason(format!(
+      "Histograms have different lengths: {} and {}",
+      first.len(),
+      second.len()
+    )));
+  }
+
+  let first = normalise(&first);
+  let second = normalise(&second);
+  let pairs = first.iter().zip(&second);
+
+  let result = match method {
+    HistogramComparison::ChiSquare => pairs
+      .filter(|(a, b)| *a + *b > 0.0)
+      .map(|(a, b)| (a - b).powi(2) / (a + b))
+      .sum(),
+    HistogramComparison::Intersection => pairs.map(|(a, b)| a.min(*b)).sum(),
+    HistogramComparison::Bhattacharyya => {
+      let coefficient: f64 = pairs.map(|(a, b)| (a * b).sqrt()).sum();
+      (1.0 - coefficient).max(0.0).sqrt()
+    }
+  };
+
+  Ok(result)
+}
@@ -0,0 +1,245 @@
+use image::{imageops, RgbaImage};
+use napi::{
+  bindgen_prelude::{AbortSignal, AsyncTask},
+  Env, Error, Task,
+};
+use std::sync::{Arc, Mutex, TryLockError};
+
+use crate::image::{AsyncFindFeatures, Cancellation, Feature, FeatureLocation, Image};
+
+const DEFAULT_SEARCH_RADIUS: u32 = 32;
+const DEFAULT_GROWTH_FACTOR: f64 = 2.0;
+
+#[napi(object)]
+#[derive(Clone, Copy, Default)]
+pub struct TrackerOptions {
+  /// Distance in pixels searched around the predicted position. Defaults to 32.
+  pub search_radius: Option<u32>,
+  /// Upper bound for the search radius as it widens after losing the target.
+  /// Defaults to searching the whole image.
+  pub max_search_radius: Option<u32>,
+  /// Factor the search radius is multiplied by for each frame the target is
+  /// not found. Defaults to 2.
+  pub growth_factor: Option<f64>,
+}
+
+#[napi(object)]
+pub struct TrackerUpdate {
+  /// Where the target was found in this frame, if it was.
+  pub location: Option<FeatureLocation>,
+  /// Movement of the target's top-left in pixels per frame.
+  pub velocity_x: f64,
+  pub velocity_y: f64,
+  /// Radius that will be searched on the next update.
+  pub search_radius: u32,
+  /// Number of consecutive updates that failed to find the target.
+  pub lost_frames: u32,
+}
+
+struct TrackerState {
+  feature: Feature,
+  color_tolerance_percent: f64,
+  max_mismatch_percent: f64,
+  x: u32,
+  y: u32,
+  width: u32,
+  height: u32,
+  velocity_x: f64,
+  velocity_y: f64,
+  base_search_radius: u32,
+  max_search_radius: u32,
+  growth_factor: f64,
+  search_radius: u32,
+  lost_frames: u32,
+}
+
+impl TrackerState {
+  /// Top-left position the target is expected at in the next frame, assuming
+  /// it kept moving at the last measured velocity while lost.
+  fn predicted_position(&self) -> (f64, f64) {
+    let frames = f64::from(self.lost_frames + 1);
+    (
+      f64::from(self.x) + self.velocity_x * frames,
+      f64::from(self.y) + self.velocity_y * frames,
+    )
+  }
+
+  fn update(
+    &mut self,
+    rgba_image: &RgbaImage,
+    cancellation: Cancellation,
+  ) -> Result<TrackerUpdate, Error> {
+    let (predicted_x, predicted_y) = self.predicted_position();
+    let radius = f64::from(self.search_radius);
+    let region_x = (predicted_x - radius).floor().max(0.0) as u32;
+    let region_y = (predicted_y - radius).floor().max(0.0) as u32;
+    let region_end_x =
+      ((predicted_x + f64::from(self.width) + radius).ceil() as u32).min(rgba_image.width());
+    let region_end_y =
+      ((predicted_y + f64::from(self.height) + radius).ceil() as u32).min(rgba_image.height());
+
+    let location = if region_x < region_end_x && region_y < region_end_y {
+      let region = imageops::crop_imm(
+        rgba_image,
+        region_x,
+        region_y,
+        region_end_x - region_x,
+        region_end_y - region_y,
+      )
+      .to_image();
+
+      AsyncFindFeatures::new(
+        self.feature.clone(),
+        self.color_tolerance_percent,
+        self.max_mismatch_percent,
+        region,
+        None,
+        cancellation,
+      )?
+      .find_locations()?
+      .into_vec()
+      .into_iter()
+      .map(|location| FeatureLocation {
+        x: location.x + region_x,
+        y: location.y + region_y,
+        anchor_x: location.anchor_x + i64::from(region_x),
+        anchor_y: location.anchor_y + i64::from(region_y),
+        ..location
+      })
+      .min_by(|a, b| {
+        let distance = |location: &FeatureLocation| {
+          (f64::from(location.x) - predicted_x).powi(2)
+            + (f64::from(location.y) - predicted_y).powi(2)
+        };
+        distance(a).total_cmp(&distance(b))
+      })
+    } else {
+      None
+    };
+
+    match &location {
+      Some(location) => {
+        let frames = f64::from(self.lost_frames + 1);
+        self.velocity_x = (f64::from(location.x) - f64::from(self.x)) / frames;
+        self.velocity_y = (f64::from(location.y) - f64::from(self.y)) / frames;
+        self.x = location.x;
+        self.y = location.y;
+        self.search_radius = self.base_search_radius;
+        self.lost_frames = 0;
+      }
+      None => {
+        self.lost_frames += 1;
+        self.search_radius = ((f64::from(self.search_radius) * self.growth_factor).ceil() as u32)
+          .max(self.search_radius.saturating_add(1))
+          .min(self.max_search_radius);
+      }
+    }
+
+    Ok(TrackerUpdate {
+      location,
+      velocity_x: self.velocity_x,
+      velocity_y: self.velocity_y,
+      search_radius: self.search_radius,
+      lost_frames: self.lost_frames,
+    })
+  }
+}
+
+pub struct AsyncTrackerUpdate {
+  state: Arc<Mutex<TrackerState>>,
+  rgba_image: RgbaImage,
+  cancellation: Cancellation,
+}
+
+#[napi]
+impl Task for AsyncTrackerUpdate {
+  type Output = TrackerUpdate;
+  type JsValue = TrackerUpdate;
+
+  fn compute(&mut self) -> Result<Self::Output, Error> {
+    let mut state = self.state.try_lock().map_err(|e| match e {
+      TryLockError::WouldBlock => {
+        Error::from_reason("Only one tracker update is allowed at a time")
+      }
+      TryLockError::Poisoned(_) => {
+        Error::from_reason("The tracker can't be used after an update panicked")
+      }
+    })?;
+    state.update(&self.rgba_image, self.cancellation.clone())
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
+    Ok(output)
+  }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue, Error> {
+    self.cancellation.reject(&env, err)
+  }
+}
+
+/// Follows a feature across successive captures, searching only a
+/// neighbourhood of where it is expected to be.
+#[napi]
+pub struct FeatureTracker {
+  state: Arc<Mutex<TrackerState>>,
+}
+
+#[napi]
+impl FeatureTracker {
+  /// Seeds the tracker with a match previously returned by `locateFeature`.
+  #[napi(constructor)]
+  pub fn new(
+    feature: Feature,
+    location: FeatureLocation,
+    max_color_distance_percent: f64,
+    max_pixel_difference_percent: f64,
+    options: Option<TrackerOptions>,
+  ) -> Self {
+    let options = options.unwrap_or_default();
+    let max_search_radius = options.max_search_radius.unwrap_or(u32::MAX);
+    let search_radius = options
+      .search_radius
+      .unwrap_or(DEFAULT_SEARCH_RADIUS)
+      .min(max_search_radius);
+
+    FeatureTracker {
+      state: Arc::new(Mutex::new(TrackerState {
+        feature,
+        color_tolerance_percent: max_color_distance_percent,
+        max_mismatch_percent: max_pixel_difference_percent,
+        x: location.x,
+        y: location.y,
+        width: location.width,
+        height: location.height,
+        velocity_x: 0.0,
+        velocity_y: 0.0,
+        base_search_radius: search_radius,
+        max_search_radius,
+        growth_factor: options
+          .growth_factor
+          .unwrap_or(DEFAULT_GROWTH_FACTOR)
+          .max(1.0),
+        search_radius,
+        lost_frames: 0,
+      })),
+    }
+  }
+
+  /// Searches the next frame for the target.
+  #[napi(ts_return_type = "Promise<TrackerUpdate>")]
+  pub fn update(
+    &self,
+    image: &Image,
+    signal: Option<AbortSignal>,
+  ) -> AsyncTask<AsyncTrackerUpdate> {
+    let cancellation = Cancellation::from_signal(signal.as_ref());
+    AsyncTask::with_optional_signal(
+      AsyncTrackerUpdate {
+        state: self.state.clone(),
+        rgba_image: image.rgba_image.clone(),
+        cancellation,
+      },
+      signal,
+    )
+  }
+}
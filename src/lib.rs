@@ -7,4 +7,5 @@ pub mod mouse;
 pub mod native_api;
 mod position;
 pub mod window;
+pub mod window_listener;
 pub mod global_listener;
@@ -30,6 +30,11 @@ impl WindowHandle {
   pub fn as_hwnd(&self) -> HWND {
     HWND(self.0 as *mut c_void)
   }
+
+  /// A handle from the value returned by `Window.id()`.
+  pub fn from_id(id: i64) -> Self {
+    Self(id as u64)
+  }
 }
 
 pub enum WindowsApiCommand {
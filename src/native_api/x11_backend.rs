@@ -2,14 +2,26 @@
 
 use image::RgbaImage;
 use once_cell::sync::OnceCell;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Once;
 use std::thread;
-use x11rb::connection::Connection;
+use std::time::{Duration, Instant};
+use x11rb::connection::{Connection, RequestConnection};
 use x11rb::errors::{ConnectionError, ReplyError};
-use x11rb::protocol::xproto::{Atom, ConnectionExt, GetPropertyType, ImageFormat, Window};
+use x11rb::protocol::composite::{self, ConnectionExt as _, Redirect};
+use x11rb::protocol::damage::{self, ConnectionExt as _, ReportLevel};
+use x11rb::protocol::xproto::{
+  Atom, AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent, ConfigureWindowAux, ConnectionExt,
+  EventMask, GetPropertyType, ImageFormat, InputFocus, MapState, StackMode, Window,
+};
+use x11rb::protocol::{ErrorKind, Event};
 use x11rb::rust_connection::RustConnection;
 
+use crate::window::{WindowBounds, WindowFilter, WindowState, WindowStateChange};
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct WindowHandle(u32);
 
@@ -37,6 +49,22 @@ pub enum X11ApiCommand {
   GetWindowRect(WindowHandle),
   IsWindowFocused(WindowHandle),
   CaptureWindowImage(WindowHandle),
+  FindWindows(WindowFilter),
+  GetWindowClass(WindowHandle),
+  GetWindowPid(WindowHandle),
+  GetWindowProcessPath(WindowHandle),
+  GetWindowRole(WindowHandle),
+  GetWindowType(WindowHandle),
+  GetWindowFrame(WindowHandle),
+  ActivateWindow(WindowHandle),
+  FocusWindow(WindowHandle),
+  RaiseWindow(WindowHandle),
+  LowerWindow(WindowHandle),
+  GetWindowState(WindowHandle),
+  WindowExists(WindowHandle),
+  SetWindowBounds(WindowHandle, WindowBounds),
+  SetWindowState(WindowHandle, WindowStateChange),
+  CloseWindow(WindowHandle),
   Shutdown,
 }
 
@@ -136,6 +164,72 @@ impl From<ReplyError> for X11ApiCaptureWindowImageError {
   }
 }
 
+#[derive(Debug, Clone)]
+pub enum X11ApiGetWindowPropertyError {
+  ConnectionError(String),
+  ReplyError(String),
+}
+
+impl From<ConnectionError> for X11ApiGetWindowPropertyError {
+  fn from(value: ConnectionError) -> Self {
+    X11ApiGetWindowPropertyError::ConnectionError(value.to_string())
+  }
+}
+
+impl From<ReplyError> for X11ApiGetWindowPropertyError {
+  fn from(value: ReplyError) -> Self {
+    X11ApiGetWindowPropertyError::ReplyError(value.to_string())
+  }
+}
+
+#[derive(Debug, Clone)]
+pub enum X11ApiGetWindowFrameError {
+  ConnectionError(String),
+  ReplyError(String),
+}
+
+impl From<ConnectionError> for X11ApiGetWindowFrameError {
+  fn from(value: ConnectionError) -> Self {
+    X11ApiGetWindowFrameError::ConnectionError(value.to_string())
+  }
+}
+
+impl From<ReplyError> for X11ApiGetWindowFrameError {
+  fn from(value: ReplyError) -> Self {
+    X11ApiGetWindowFrameError::ReplyError(value.to_string())
+  }
+}
+
+#[derive(Debug, Clone)]
+pub enum X11ApiWindowActionError {
+  ConnectionError(String),
+  ReplyError(String),
+}
+
+impl From<ConnectionError> for X11ApiWindowActionError {
+  fn from(value: ConnectionError) -> Self {
+    X11ApiWindowActionError::ConnectionError(value.to_string())
+  }
+}
+
+impl From<ReplyError> for X11ApiWindowActionError {
+  fn from(value: ReplyError) -> Self {
+    X11ApiWindowActionError::ReplyError(value.to_string())
+  }
+}
+
+#[derive(Debug, Clone)]
+pub enum X11ApiFindWindowsError {
+  EnumerateWindows(X11ApiEnumerateWindowsError),
+  ConnectionError(String),
+}
+
+impl From<X11ApiEnumerateWindowsError> for X11ApiFindWindowsError {
+  fn from(value: X11ApiEnumerateWindowsError) -> Self {
+    X11ApiFindWindowsError::EnumerateWindows(value)
+  }
+}
+
 #[derive(Debug, Clone)]
 pub enum X11ApiError {
   EnumerateWindows(X11ApiEnumerateWindowsError),
@@ -143,6 +237,10 @@ pub enum X11ApiError {
   GetWindowRect(X11ApiGetWindowRectError),
   IsWindowFocused(X11ApiIsWindowFocusedError),
   CaptureWindowImage(X11ApiCaptureWindowImageError),
+  FindWindows(X11ApiFindWindowsError),
+  GetWindowProperty(X11ApiGetWindowPropertyError),
+  GetWindowFrame(X11ApiGetWindowFrameError),
+  WindowAction(X11ApiWindowActionError),
 }
 
 pub enum X11ApiResponse {
@@ -151,10 +249,80 @@ pub enum X11ApiResponse {
   WindowRect(Rect),
   WindowFocused(bool),
   WindowImage(RgbaImage),
+  /// The window was just redirected and its client is still repainting it;
+  /// capture it again shortly.
+  WindowRepainting,
+  WindowClass(Option<(String, String)>),
+  WindowPid(Option<u32>),
+  WindowProcessPath(Option<PathBuf>),
+  WindowRole(Option<String>),
+  WindowType(Vec<String>),
+  WindowFrame(WindowHandle),
+  WindowState(WindowState),
+  WindowExists(bool),
   Error(X11ApiError),
   Acknowledgement,
 }
 
+/// Atoms used to tell application windows apart from frames and helpers.
+struct EnumerationAtoms {
+  net_client_list_stacking: Atom,
+  net_client_list: Atom,
+  wm_state: Atom,
+  net_wm_name: Atom,
+}
+
+/// Atoms for asking the window manager to change windows, as described by
+/// EWMH.
+struct WindowActionAtoms {
+  net_supported: Atom,
+  net_active_window: Atom,
+  net_close_window: Atom,
+  net_wm_state: Atom,
+  net_wm_state_hidden: Atom,
+  net_wm_state_maximized_vert: Atom,
+  net_wm_state_maximized_horz: Atom,
+  net_wm_state_fullscreen: Atom,
+  net_wm_state_above: Atom,
+  wm_state: Atom,
+  wm_change_state: Atom,
+  wm_protocols: Atom,
+  wm_delete_window: Atom,
+}
+
+/// Windows this connection redirected off-screen with the Composite extension.
+/// They stay redirected so the server keeps their contents up to date, even
+/// while covered, between captures, until they are destroyed.
+struct CompositeState {
+  redirected: HashSet<Window>,
+  /// Newly redirected windows whose clients may still be repainting the parts
+  /// that were covered.
+  repainting: HashMap<Window, Repaint>,
+  /// Whether the Damage extension can report when a newly redirected window
+  /// has been repainted.
+  damage: bool,
+}
+
+struct Repaint {
+  damage: damage::Damage,
+  since: Instant,
+  last_damage: Option<Instant>,
+}
+
+impl Repaint {
+  fn is_settled(&self, now: Instant) -> bool {
+    now - self.since >= REPAINT_TIMEOUT
+      || self
+        .last_damage
+        .is_some_and(|last| now - last >= REPAINT_SETTLE)
+  }
+}
+
+/// How long the first capture of a window waits for its client to repaint the
+/// parts that were covered, and how long drawing must pause to count as done.
+const REPAINT_TIMEOUT: Duration = Duration::from_millis(250);
+const REPAINT_SETTLE: Duration = Duration::from_millis(20);
+
 fn x11_api_thread_main(receiver: Receiver<(X11ApiCommand, Sender<X11ApiResponse>)>) {
   let (conn, screen_num) = match x11rb::connect(None) {
     Ok(c) => c,
@@ -179,11 +347,71 @@ fn x11_api_thread_main(receiver: Receiver<(X11ApiCommand, Sender<X11ApiResponse>
     .reply()
     .unwrap()
     .atom;
+  let net_wm_pid = conn
+    .intern_atom(false, b"_NET_WM_PID")
+    .unwrap()
+    .reply()
+    .unwrap()
+    .atom;
+  let wm_window_role = conn
+    .intern_atom(false, b"WM_WINDOW_ROLE")
+    .unwrap()
+    .reply()
+    .unwrap()
+    .atom;
+  let net_wm_window_type = conn
+    .intern_atom(false, b"_NET_WM_WINDOW_TYPE")
+    .unwrap()
+    .reply()
+    .unwrap()
+    .atom;
+  let enumeration_atoms = EnumerationAtoms {
+    net_client_list_stacking: conn
+      .intern_atom(false, b"_NET_CLIENT_LIST_STACKING")
+      .unwrap()
+      .reply()
+      .unwrap()
+      .atom,
+    net_client_list: conn
+      .intern_atom(false, b"_NET_CLIENT_LIST")
+      .unwrap()
+      .reply()
+      .unwrap()
+      .atom,
+    wm_state: conn
+      .intern_atom(false, b"WM_STATE")
+      .unwrap()
+      .reply()
+      .unwrap()
+      .atom,
+    net_wm_name,
+  };
+
+  let intern = |name: &[u8]| conn.intern_atom(false, name).unwrap().reply().unwrap().atom;
+  let action_atoms = WindowActionAtoms {
+    net_supported: intern(b"_NET_SUPPORTED"),
+    net_active_window: intern(b"_NET_ACTIVE_WINDOW"),
+    net_close_window: intern(b"_NET_CLOSE_WINDOW"),
+    net_wm_state: intern(b"_NET_WM_STATE"),
+    net_wm_state_hidden: intern(b"_NET_WM_STATE_HIDDEN"),
+    net_wm_state_maximized_vert: intern(b"_NET_WM_STATE_MAXIMIZED_VERT"),
+    net_wm_state_maximized_horz: intern(b"_NET_WM_STATE_MAXIMIZED_HORZ"),
+    net_wm_state_fullscreen: intern(b"_NET_WM_STATE_FULLSCREEN"),
+    net_wm_state_above: intern(b"_NET_WM_STATE_ABOVE"),
+    wm_state: enumeration_atoms.wm_state,
+    wm_change_state: intern(b"WM_CHANGE_STATE"),
+    wm_protocols: intern(b"WM_PROTOCOLS"),
+    wm_delete_window: intern(b"WM_DELETE_WINDOW"),
+  };
+
+  let mut composite_state = query_composite(&conn);
 
   while let Ok((command, response_sender)) = receiver.recv() {
+    handle_events(&conn, composite_state.as_mut());
+
     match command {
       X11ApiCommand::EnumerateWindows => {
-        let response = match enumerate_windows(&conn, root_window, net_wm_name) {
+        let response = match enumerate_windows(&conn, root_window, &enumeration_atoms) {
           Ok(windows) => X11ApiResponse::WindowList(windows),
           Err(e) => X11ApiResponse::Error(X11ApiError::EnumerateWindows(e)),
         };
@@ -211,9 +439,151 @@ fn x11_api_thread_main(receiver: Receiver<(X11ApiCommand, Sender<X11ApiResponse>
         response_sender.send(response).ok();
       }
       X11ApiCommand::CaptureWindowImage(handle) => {
-        let response = match capture_window_image(&conn, handle.as_window()) {
-          Ok(img) => X11ApiResponse::WindowImage(img),
-          Err(e) => X11ApiResponse::Error(X11ApiError::CaptureWindowImage(e)),
+        // Composite reads windows that are covered or off-screen; reading the
+        // window directly is the fallback when it's unavailable or fails.
+        let composited = composite_state.as_mut().and_then(|composite_state| {
+          capture_window_image_with_composite(
+            &conn,
+            root_window,
+            handle.as_window(),
+            composite_state,
+          )
+          .ok()
+        });
+        let response = match composited {
+          Some(Some(img)) => X11ApiResponse::WindowImage(img),
+          Some(None) => X11ApiResponse::WindowRepainting,
+          None => match capture_window_image(&conn, handle.as_window()) {
+            Ok(img) => X11ApiResponse::WindowImage(img),
+            Err(e) => X11ApiResponse::Error(X11ApiError::CaptureWindowImage(e)),
+          },
+        };
+        response_sender.send(response).ok();
+      }
+      X11ApiCommand::FindWindows(filter) => {
+        let response = match find_windows(
+          &conn,
+          root_window,
+          &filter,
+          &enumeration_atoms,
+          net_wm_name,
+          utf8_string,
+          net_wm_pid,
+        ) {
+          Ok(windows) => X11ApiResponse::WindowList(windows),
+          Err(e) => X11ApiResponse::Error(X11ApiError::FindWindows(e)),
+        };
+        response_sender.send(response).ok();
+      }
+      X11ApiCommand::GetWindowClass(handle) => {
+        let response = match get_window_class(&conn, handle.as_window()) {
+          Ok(class) => X11ApiResponse::WindowClass(class),
+          Err(e) => X11ApiResponse::Error(X11ApiError::GetWindowProperty(e)),
+        };
+        response_sender.send(response).ok();
+      }
+      X11ApiCommand::GetWindowPid(handle) => {
+        let response = match get_window_pid(&conn, handle.as_window(), net_wm_pid) {
+          Ok(pid) => X11ApiResponse::WindowPid(pid),
+          Err(e) => X11ApiResponse::Error(X11ApiError::GetWindowProperty(e)),
+        };
+        response_sender.send(response).ok();
+      }
+      X11ApiCommand::GetWindowProcessPath(handle) => {
+        let response = match get_window_pid(&conn, handle.as_window(), net_wm_pid) {
+          Ok(pid) => X11ApiResponse::WindowProcessPath(pid.and_then(process_path)),
+          Err(e) => X11ApiResponse::Error(X11ApiError::GetWindowProperty(e)),
+        };
+        response_sender.send(response).ok();
+      }
+      X11ApiCommand::GetWindowRole(handle) => {
+        let response = match get_window_role(&conn, handle.as_window(), wm_window_role) {
+          Ok(role) => X11ApiResponse::WindowRole(role),
+          Err(e) => X11ApiResponse::Error(X11ApiError::GetWindowProperty(e)),
+        };
+        response_sender.send(response).ok();
+      }
+      X11ApiCommand::GetWindowType(handle) => {
+        let response = match get_window_type(&conn, handle.as_window(), net_wm_window_type) {
+          Ok(types) => X11ApiResponse::WindowType(types),
+          Err(e) => X11ApiResponse::Error(X11ApiError::GetWindowProperty(e)),
+        };
+        response_sender.send(response).ok();
+      }
+      X11ApiCommand::GetWindowFrame(handle) => {
+        let response = match get_window_frame(&conn, root_window, handle.as_window()) {
+          Ok(frame) => X11ApiResponse::WindowFrame(WindowHandle::new(frame)),
+          Err(e) => X11ApiResponse::Error(X11ApiError::GetWindowFrame(e)),
+        };
+        response_sender.send(response).ok();
+      }
+      X11ApiCommand::ActivateWindow(handle) => {
+        let response = match activate_window(&conn, root_window, handle.as_window(), &action_atoms)
+        {
+          Ok(()) => X11ApiResponse::Acknowledgement,
+          Err(e) => X11ApiResponse::Error(X11ApiError::WindowAction(e)),
+        };
+        response_sender.send(response).ok();
+      }
+      X11ApiCommand::FocusWindow(handle) => {
+        let response = match focus_and_raise(&conn, handle.as_window()) {
+          Ok(()) => X11ApiResponse::Acknowledgement,
+          Err(e) => X11ApiResponse::Error(X11ApiError::WindowAction(e)),
+        };
+        response_sender.send(response).ok();
+      }
+      X11ApiCommand::RaiseWindow(handle) => {
+        let response = match restack_window(&conn, handle.as_window(), StackMode::ABOVE) {
+          Ok(()) => X11ApiResponse::Acknowledgement,
+          Err(e) => X11ApiResponse::Error(X11ApiError::WindowAction(e)),
+        };
+        response_sender.send(response).ok();
+      }
+      X11ApiCommand::LowerWindow(handle) => {
+        let response = match restack_window(&conn, handle.as_window(), StackMode::BELOW) {
+          Ok(()) => X11ApiResponse::Acknowledgement,
+          Err(e) => X11ApiResponse::Error(X11ApiError::WindowAction(e)),
+        };
+        response_sender.send(response).ok();
+      }
+      X11ApiCommand::GetWindowState(handle) => {
+        let response = match get_window_state(&conn, handle.as_window(), &action_atoms) {
+          Ok(state) => X11ApiResponse::WindowState(state),
+          Err(e) => X11ApiResponse::Error(X11ApiError::GetWindowProperty(e)),
+        };
+        response_sender.send(response).ok();
+      }
+      X11ApiCommand::WindowExists(handle) => {
+        let response = match window_exists(&conn, handle.as_window()) {
+          Ok(exists) => X11ApiResponse::WindowExists(exists),
+          Err(e) => X11ApiResponse::Error(X11ApiError::GetWindowProperty(e)),
+        };
+        response_sender.send(response).ok();
+      }
+      X11ApiCommand::SetWindowBounds(handle, bounds) => {
+        let response = match set_window_bounds(&conn, handle.as_window(), bounds) {
+          Ok(()) => X11ApiResponse::Acknowledgement,
+          Err(e) => X11ApiResponse::Error(X11ApiError::WindowAction(e)),
+        };
+        response_sender.send(response).ok();
+      }
+      X11ApiCommand::SetWindowState(handle, change) => {
+        let response = match set_window_state(
+          &conn,
+          root_window,
+          handle.as_window(),
+          change,
+          &action_atoms,
+        ) {
+          Ok(()) => X11ApiResponse::Acknowledgement,
+          Err(e) => X11ApiResponse::Error(X11ApiError::WindowAction(e)),
+        };
+        response_sender.send(response).ok();
+      }
+      X11ApiCommand::CloseWindow(handle) => {
+        let response = match close_window(&conn, root_window, handle.as_window(), &action_atoms) {
+          Ok(()) => X11ApiResponse::Acknowledgement,
+          Err(e) => X11ApiResponse::Error(X11ApiError::WindowAction(e)),
         };
         response_sender.send(response).ok();
       }
@@ -225,7 +595,72 @@ fn x11_api_thread_main(receiver: Receiver<(X11ApiCommand, Sender<X11ApiResponse>
   }
 }
 
+/// Lists application (client) windows. Under a reparenting window manager the
+/// root's children are frames, so the window manager's client list is used
+/// when there is one, and otherwise the whole tree is searched for windows with
+/// the ICCCM `WM_STATE` property. With no window manager at all, named
+/// top-level windows are returned.
 fn enumerate_windows(
+  conn: &RustConnection,
+  root: Window,
+  atoms: &EnumerationAtoms,
+) -> Result<Vec<WindowHandle>, X11ApiEnumerateWindowsError> {
+  // The stacking list is ordered bottom to top, as the root's children are.
+  for client_list in [atoms.net_client_list_stacking, atoms.net_client_list] {
+    let clients: Vec<WindowHandle> = conn
+      .get_property(false, root, client_list, AtomEnum::WINDOW, 0, u32::MAX)
+      .ok()
+      .and_then(|cookie| cookie.reply().ok())
+      .and_then(|prop| Some(prop.value32()?.map(WindowHandle::new).collect()))
+      .unwrap_or_default();
+    if !clients.is_empty() {
+      return Ok(clients);
+    }
+  }
+
+  let clients = find_clients_in_tree(conn, root, atoms.wm_state)?;
+  if !clients.is_empty() {
+    return Ok(clients);
+  }
+
+  enumerate_named_top_level_windows(conn, root, atoms.net_wm_name)
+}
+
+/// Walks the window tree depth first, in stacking order, collecting the
+/// windows that carry `WM_STATE` without descending into them.
+fn find_clients_in_tree(
+  conn: &RustConnection,
+  root: Window,
+  wm_state: Atom,
+) -> Result<Vec<WindowHandle>, X11ApiEnumerateWindowsError> {
+  let mut clients = Vec::new();
+  let mut pending: Vec<Window> = conn.query_tree(root)?.reply()?.children;
+  pending.reverse();
+
+  while let Some(window) = pending.pop() {
+    let has_wm_state = conn
+      .get_property(false, window, wm_state, GetPropertyType::ANY, 0, 0)
+      .ok()
+      .and_then(|cookie| cookie.reply().ok())
+      .is_some_and(|prop| prop.type_ != u32::from(AtomEnum::NONE));
+    if has_wm_state {
+      clients.push(WindowHandle::new(window));
+      continue;
+    }
+
+    // Windows can disappear while we walk, so skip any we can no longer query.
+    if let Some(tree) = conn
+      .query_tree(window)
+      .ok()
+      .and_then(|cookie| cookie.reply().ok())
+    {
+      pending.extend(tree.children.into_iter().rev());
+    }
+  }
+  Ok(clients)
+}
+
+fn enumerate_named_top_level_windows(
   conn: &RustConnection,
   root: Window,
   net_wm_name: Atom,
@@ -271,15 +706,474 @@ fn get_window_title(
   Ok(String::from_utf8(prop.value).unwrap_or_default())
 }
 
+/// Reads `WM_CLASS`, which holds the instance and class names as two
+/// NUL-terminated strings.
+fn get_window_class(
+  conn: &RustConnection,
+  window: Window,
+) -> Result<Option<(String, String)>, X11ApiGetWindowPropertyError> {
+  let prop = conn
+    .get_property(false, window, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, 1024)?
+    .reply()?;
+  let mut parts = prop
+    .value
+    .split(|&byte| byte == 0)
+    .map(|part| String::from_utf8_lossy(part).into_owned());
+
+  Ok(
+    parts
+      .next()
+      .filter(|instance| !instance.is_empty())
+      .map(|instance| (instance, parts.next().unwrap_or_default())),
+  )
+}
+
+fn get_window_pid(
+  conn: &RustConnection,
+  window: Window,
+  net_wm_pid: Atom,
+) -> Result<Option<u32>, X11ApiGetWindowPropertyError> {
+  let prop = conn
+    .get_property(false, window, net_wm_pid, AtomEnum::CARDINAL, 0, 1)?
+    .reply()?;
+
+  Ok(prop.value32().and_then(|mut values| values.next()))
+}
+
+fn get_window_role(
+  conn: &RustConnection,
+  window: Window,
+  wm_window_role: Atom,
+) -> Result<Option<String>, X11ApiGetWindowPropertyError> {
+  let prop = conn
+    .get_property(false, window, wm_window_role, GetPropertyType::ANY, 0, 1024)?
+    .reply()?;
+  let role = String::from_utf8_lossy(&prop.value)
+    .trim_end_matches('\0')
+    .to_string();
+
+  Ok(Some(role).filter(|role| !role.is_empty()))
+}
+
+/// Reads `_NET_WM_WINDOW_TYPE`, returning each type without its
+/// `_NET_WM_WINDOW_TYPE_` prefix and in lower case, such as `dialog`.
+fn get_window_type(
+  conn: &RustConnection,
+  window: Window,
+  net_wm_window_type: Atom,
+) -> Result<Vec<String>, X11ApiGetWindowPropertyError> {
+  let prop = conn
+    .get_property(false, window, net_wm_window_type, AtomEnum::ATOM, 0, 32)?
+    .reply()?;
+  let Some(atoms) = prop.value32() else {
+    return Ok(Vec::new());
+  };
+
+  let cookies = atoms
+    .map(|atom| conn.get_atom_name(atom))
+    .collect::<Result<Vec<_>, _>>()?;
+
+  let mut types = Vec::new();
+  for cookie in cookies {
+    let name = cookie.reply()?.name;
+    let name = String::from_utf8_lossy(&name);
+    types.push(
+      name
+        .strip_prefix("_NET_WM_WINDOW_TYPE_")
+        .unwrap_or(&name)
+        .to_lowercase(),
+    );
+  }
+  Ok(types)
+}
+
+/// The executable behind a process, which needs permission to read the
+/// `/proc/<pid>/exe` link.
+fn process_path(pid: u32) -> Option<PathBuf> {
+  fs::read_link(Path::new("/proc").join(pid.to_string()).join("exe")).ok()
+}
+
+/// Names a process is known by: the executable's file name and the kernel's
+/// short name, which differ for interpreters and truncated names.
+fn process_names(pid: u32) -> Vec<String> {
+  let mut names = Vec::new();
+  if let Some(name) =
+    process_path(pid).and_then(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+  {
+    names.push(name);
+  }
+  if let Ok(comm) = fs::read_to_string(Path::new("/proc").join(pid.to_string()).join("comm")) {
+    names.push(comm.trim_end().to_string());
+  }
+  names
+}
+
+/// Windows destroyed while the filter reads them fail with an X11 error and
+/// don't match; losing the connection is an error.
+fn window_matches_filter(
+  conn: &RustConnection,
+  window: Window,
+  filter: &WindowFilter,
+  net_wm_name: Atom,
+  utf8_string: Atom,
+  net_wm_pid: Atom,
+) -> Result<bool, X11ApiFindWindowsError> {
+  use X11ApiGetWindowPropertyError::ConnectionError;
+
+  // Cheapest checks first, so most windows are rejected after one request.
+  if let Some(class_name) = &filter.class_name {
+    match get_window_class(conn, window) {
+      Ok(Some((instance, class))) if &instance == class_name || &class == class_name => {}
+      Err(ConnectionError(e)) => return Err(X11ApiFindWindowsError::ConnectionError(e)),
+      _ => return Ok(false),
+    }
+  }
+
+  if filter.pid.is_some() || filter.process_name.is_some() {
+    let pid = match get_window_pid(conn, window, net_wm_pid) {
+      Ok(Some(pid)) => pid,
+      Err(ConnectionError(e)) => return Err(X11ApiFindWindowsError::ConnectionError(e)),
+      _ => return Ok(false),
+    };
+    if filter.pid.is_some_and(|expected| expected != pid) {
+      return Ok(false);
+    }
+    if let Some(process_name) = &filter.process_name {
+      if !process_names(pid).contains(process_name) {
+        return Ok(false);
+      }
+    }
+  }
+
+  if filter.needs_title() {
+    match get_window_title(conn, window, net_wm_name, utf8_string) {
+      Ok(title) if filter.matches_title(&title) => {}
+      Err(X11ApiGetWindowTitleError::GetPropertyConnectionError(e)) => {
+        return Err(X11ApiFindWindowsError::ConnectionError(e))
+      }
+      _ => return Ok(false),
+    }
+  }
+
+  Ok(true)
+}
+
+fn find_windows(
+  conn: &RustConnection,
+  root: Window,
+  filter: &WindowFilter,
+  enumeration_atoms: &EnumerationAtoms,
+  net_wm_name: Atom,
+  utf8_string: Atom,
+  net_wm_pid: Atom,
+) -> Result<Vec<WindowHandle>, X11ApiFindWindowsError> {
+  let mut windows = Vec::new();
+  for handle in enumerate_windows(conn, root, enumeration_atoms)? {
+    if window_matches_filter(
+      conn,
+      handle.as_window(),
+      filter,
+      net_wm_name,
+      utf8_string,
+      net_wm_pid,
+    )? {
+      windows.push(handle);
+    }
+  }
+  Ok(windows)
+}
+
+/// Finds the top-level ancestor a window manager reparented the window into,
+/// which is the window itself when it isn't reparented.
+fn get_window_frame(
+  conn: &RustConnection,
+  root: Window,
+  window: Window,
+) -> Result<Window, X11ApiGetWindowFrameError> {
+  let mut current = window;
+  loop {
+    let parent = conn.query_tree(current)?.reply()?.parent;
+    if parent == root || parent == x11rb::NONE {
+      return Ok(current);
+    }
+    current = parent;
+  }
+}
+
+fn window_manager_supports(
+  conn: &RustConnection,
+  root: Window,
+  net_supported: Atom,
+  atom: Atom,
+) -> Result<bool, X11ApiWindowActionError> {
+  let prop = conn
+    .get_property(false, root, net_supported, AtomEnum::ATOM, 0, u32::MAX)?
+    .reply()?;
+
+  Ok(
+    prop
+      .value32()
+      .is_some_and(|mut atoms| atoms.any(|supported| supported == atom)),
+  )
+}
+
+/// Sends a request to the window manager about a client window.
+fn send_window_manager_message(
+  conn: &RustConnection,
+  root: Window,
+  window: Window,
+  message_type: Atom,
+  data: [u32; 5],
+) -> Result<(), X11ApiWindowActionError> {
+  let event = ClientMessageEvent::new(32, window, message_type, data);
+  conn.send_event(
+    false,
+    root,
+    EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+    event,
+  )?;
+  Ok(())
+}
+
+/// Asks the window manager to activate the window, which also switches to its
+/// desktop and raises it. Without an EWMH window manager the window is mapped,
+/// raised and given input focus directly. Neither waits for the result.
+fn activate_window(
+  conn: &RustConnection,
+  root: Window,
+  window: Window,
+  atoms: &WindowActionAtoms,
+) -> Result<(), X11ApiWindowActionError> {
+  if window_manager_supports(conn, root, atoms.net_supported, atoms.net_active_window)? {
+    // Source indication 2 marks the request as coming from a pager or other
+    // tool acting for the user, which window managers don't refuse as focus
+    // stealing.
+    send_window_manager_message(
+      conn,
+      root,
+      window,
+      atoms.net_active_window,
+      [2, x11rb::CURRENT_TIME, 0, 0, 0],
+    )?;
+    conn.flush()?;
+    Ok(())
+  } else {
+    focus_and_raise(conn, window)
+  }
+}
+
+/// Maps and raises the window, bypassing the window manager's activation, and
+/// focuses it once it's viewable. Focusing a window that isn't viewable yet,
+/// such as while a window manager handles its map request, fails with
+/// BadMatch, so callers repeat this until the window has focus.
+fn focus_and_raise(conn: &RustConnection, window: Window) -> Result<(), X11ApiWindowActionError> {
+  conn.map_window(window)?;
+  conn.configure_window(
+    window,
+    &ConfigureWindowAux::new().stack_mode(StackMode::ABOVE),
+  )?;
+  if conn.get_window_attributes(window)?.reply()?.map_state == MapState::VIEWABLE {
+    conn.set_input_focus(InputFocus::PARENT, window, x11rb::CURRENT_TIME)?;
+  }
+  conn.flush()?;
+  Ok(())
+}
+
+/// Restacks through `ConfigureWindow`, which the window manager intercepts and
+/// applies to the window's frame.
+fn restack_window(
+  conn: &RustConnection,
+  window: Window,
+  stack_mode: StackMode,
+) -> Result<(), X11ApiWindowActionError> {
+  conn.configure_window(window, &ConfigureWindowAux::new().stack_mode(stack_mode))?;
+  conn.flush()?;
+  Ok(())
+}
+
+fn get_window_state(
+  conn: &RustConnection,
+  window: Window,
+  atoms: &WindowActionAtoms,
+) -> Result<WindowState, X11ApiGetWindowPropertyError> {
+  let states: Vec<Atom> = conn
+    .get_property(false, window, atoms.net_wm_state, AtomEnum::ATOM, 0, 64)?
+    .reply()?
+    .value32()
+    .map(|states| states.collect())
+    .unwrap_or_default();
+  // ICCCM's WM_STATE is 3 while iconified, for window managers that don't set
+  // `_NET_WM_STATE_HIDDEN`.
+  let iconic = conn
+    .get_property(false, window, atoms.wm_state, atoms.wm_state, 0, 1)?
+    .reply()?
+    .value32()
+    .and_then(|mut values| values.next())
+    == Some(3);
+
+  Ok(WindowState {
+    minimized: iconic || states.contains(&atoms.net_wm_state_hidden),
+    maximized: states.contains(&atoms.net_wm_state_maximized_vert)
+      && states.contains(&atoms.net_wm_state_maximized_horz),
+    fullscreen: states.contains(&atoms.net_wm_state_fullscreen),
+    always_on_top: states.contains(&atoms.net_wm_state_above),
+  })
+}
+
+/// Only `BadWindow` means the window is gone; any other failure, such as a
+/// lost connection, is reported rather than taken as a missing window.
+fn window_exists(
+  conn: &RustConnection,
+  window: Window,
+) -> Result<bool, X11ApiGetWindowPropertyError> {
+  match conn.get_window_attributes(window)?.reply() {
+    Ok(_) => Ok(true),
+    Err(ReplyError::X11Error(error)) if error.error_kind == ErrorKind::Window => Ok(false),
+    Err(error) => Err(error.into()),
+  }
+}
+
+fn set_window_bounds(
+  conn: &RustConnection,
+  window: Window,
+  bounds: WindowBounds,
+) -> Result<(), X11ApiWindowActionError> {
+  let aux = ConfigureWindowAux::new()
+    .x(bounds.x)
+    .y(bounds.y)
+    .width(bounds.width)
+    .height(bounds.height);
+  conn.configure_window(window, &aux)?;
+  conn.flush()?;
+  Ok(())
+}
+
+/// Adds or removes up to two `_NET_WM_STATE` atoms; pass 0 for an unused one.
+fn change_net_wm_state(
+  conn: &RustConnection,
+  root: Window,
+  window: Window,
+  atoms: &WindowActionAtoms,
+  add: bool,
+  first: Atom,
+  second: Atom,
+) -> Result<(), X11ApiWindowActionError> {
+  send_window_manager_message(
+    conn,
+    root,
+    window,
+    atoms.net_wm_state,
+    [u32::from(add), first, second, 2, 0],
+  )
+}
+
+fn set_window_state(
+  conn: &RustConnection,
+  root: Window,
+  window: Window,
+  change: WindowStateChange,
+  atoms: &WindowActionAtoms,
+) -> Result<(), X11ApiWindowActionError> {
+  let maximized = (
+    atoms.net_wm_state_maximized_vert,
+    atoms.net_wm_state_maximized_horz,
+  );
+  match change {
+    WindowStateChange::Minimize => {
+      // Asks for ICCCM's IconicState.
+      send_window_manager_message(conn, root, window, atoms.wm_change_state, [3, 0, 0, 0, 0])?;
+    }
+    WindowStateChange::Maximize => {
+      change_net_wm_state(conn, root, window, atoms, true, maximized.0, maximized.1)?;
+    }
+    WindowStateChange::Restore => {
+      // Mapping an iconified window returns it to the normal state.
+      conn.map_window(window)?;
+      change_net_wm_state(conn, root, window, atoms, false, maximized.0, maximized.1)?;
+      change_net_wm_state(
+        conn,
+        root,
+        window,
+        atoms,
+        false,
+        atoms.net_wm_state_fullscreen,
+        0,
+      )?;
+    }
+    WindowStateChange::Fullscreen(fullscreen) => {
+      change_net_wm_state(
+        conn,
+        root,
+        window,
+        atoms,
+        fullscreen,
+        atoms.net_wm_state_fullscreen,
+        0,
+      )?;
+    }
+    WindowStateChange::AlwaysOnTop(always_on_top) => {
+      change_net_wm_state(
+        conn,
+        root,
+        window,
+        atoms,
+        always_on_top,
+        atoms.net_wm_state_above,
+        0,
+      )?;
+    }
+  }
+
+  conn.flush()?;
+  Ok(())
+}
+
+/// Asks the client to close the window through `WM_DELETE_WINDOW`, or the
+/// window manager through `_NET_CLOSE_WINDOW`. Clients supporting neither are
+/// disconnected, as `xkill` does.
+fn close_window(
+  conn: &RustConnection,
+  root: Window,
+  window: Window,
+  atoms: &WindowActionAtoms,
+) -> Result<(), X11ApiWindowActionError> {
+  let supports_delete_window = conn
+    .get_property(false, window, atoms.wm_protocols, AtomEnum::ATOM, 0, 32)?
+    .reply()?
+    .value32()
+    .is_some_and(|mut protocols| protocols.any(|protocol| protocol == atoms.wm_delete_window));
+
+  if supports_delete_window {
+    let event = ClientMessageEvent::new(
+      32,
+      window,
+      atoms.wm_protocols,
+      [atoms.wm_delete_window, x11rb::CURRENT_TIME, 0, 0, 0],
+    );
+    conn.send_event(false, window, EventMask::NO_EVENT, event)?;
+  } else if window_manager_supports(conn, root, atoms.net_supported, atoms.net_close_window)? {
+    send_window_manager_message(
+      conn,
+      root,
+      window,
+      atoms.net_close_window,
+      [x11rb::CURRENT_TIME, 2, 0, 0, 0],
+    )?;
+  } else {
+    conn.kill_client(window)?;
+  }
+
+  conn.flush()?;
+  Ok(())
+}
+
 fn get_window_rect(
   conn: &RustConnection,
   root: Window,
   window: Window,
 ) -> Result<Rect, X11ApiGetWindowRectError> {
   let geom = conn.get_geometry(window)?.reply()?;
-  let translated = conn
-    .translate_coordinates(window, root, geom.x, geom.y)?
-    .reply()?;
+  let translated = conn.translate_coordinates(window, root, 0, 0)?.reply()?;
 
   Ok(Rect {
     left: translated.dst_x as i32,
@@ -326,6 +1220,181 @@ fn capture_window_image(
     .ok_or_else(|| X11ApiCaptureWindowImageError::InvalidBitmap)
 }
 
+fn query_composite(conn: &RustConnection) -> Option<CompositeState> {
+  conn
+    .extension_information(composite::X11_EXTENSION_NAME)
+    .ok()??;
+  let version = conn.composite_query_version(0, 4).ok()?.reply().ok()?;
+
+  let damage = conn
+    .extension_information(damage::X11_EXTENSION_NAME)
+    .ok()
+    .flatten()
+    .is_some()
+    && conn
+      .damage_query_version(1, 1)
+      .ok()
+      .and_then(|cookie| cookie.reply().ok())
+      .is_some();
+
+  // NameWindowPixmap was added in 0.2.
+  (version.major_version > 0 || version.minor_version >= 2).then(|| CompositeState {
+    redirected: HashSet::new(),
+    repainting: HashMap::new(),
+    damage,
+  })
+}
+
+/// Handles the events queued since the last command, before they pile up.
+/// Redirected windows report their destruction through `StructureNotify`, so
+/// that a reused window id is redirected again, and their repainting through
+/// Damage. Other events, and errors from requests that weren't checked, are
+/// dropped.
+fn handle_events(conn: &RustConnection, mut composite_state: Option<&mut CompositeState>) {
+  while let Ok(Some(event)) = conn.poll_for_event() {
+    let Some(composite_state) = composite_state.as_deref_mut() else {
+      continue;
+    };
+    match event {
+      Event::DestroyNotify(e) => {
+        composite_state.redirected.remove(&e.window);
+        composite_state.repainting.remove(&e.window);
+      }
+      Event::DamageNotify(e) => {
+        if let Some(repaint) = composite_state.repainting.get_mut(&e.drawable) {
+          repaint.last_damage = Some(Instant::now());
+        }
+      }
+      _ => {}
+    }
+  }
+}
+
+/// Redirects a window off-screen. Parts of it that were covered only have
+/// contents once its client repaints them, so with the Damage extension the
+/// window is tracked as repainting until its client stops drawing, up to
+/// `REPAINT_TIMEOUT`. Without it, or when a client is slower than that, those
+/// parts may be captured blank.
+fn redirect_window(
+  conn: &RustConnection,
+  window: Window,
+  composite_state: &mut CompositeState,
+) -> Result<(), X11ApiCaptureWindowImageError> {
+  conn
+    .change_window_attributes(
+      window,
+      &ChangeWindowAttributesAux::new().event_mask(EventMask::STRUCTURE_NOTIFY),
+    )?
+    .check()?;
+
+  let damage = if composite_state.damage {
+    let damage = conn
+      .generate_id()
+      .map_err(|e| X11ApiCaptureWindowImageError::Generic(e.to_string()))?;
+    conn.damage_create(damage, window, ReportLevel::DELTA_RECTANGLES)?;
+    Some(damage)
+  } else {
+    None
+  };
+
+  let redirected = conn
+    .composite_redirect_window(window, Redirect::AUTOMATIC)
+    .map_err(X11ApiCaptureWindowImageError::from)
+    .and_then(|cookie| Ok(cookie.check()?));
+
+  match (&redirected, damage) {
+    (Ok(()), Some(damage)) => {
+      composite_state.redirected.insert(window);
+      composite_state.repainting.insert(
+        window,
+        Repaint {
+          damage,
+          since: Instant::now(),
+          last_damage: None,
+        },
+      );
+    }
+    (Ok(()), None) => {
+      composite_state.redirected.insert(window);
+    }
+    (Err(_), Some(damage)) => {
+      conn.damage_destroy(damage)?;
+    }
+    (Err(_), None) => {}
+  }
+
+  redirected
+}
+
+/// Reads part of a window's off-screen pixmap, which only exists while the
+/// window is redirected.
+fn read_window_pixmap(
+  conn: &RustConnection,
+  window: Window,
+  (x, y): (i16, i16),
+  (width, height): (u16, u16),
+) -> Result<RgbaImage, X11ApiCaptureWindowImageError> {
+  let depth = conn.get_geometry(window)?.reply()?.depth;
+  let pixmap = conn
+    .generate_id()
+    .map_err(|e| X11ApiCaptureWindowImageError::Generic(e.to_string()))?;
+  conn.composite_name_window_pixmap(window, pixmap)?.check()?;
+
+  let img = conn
+    .get_image(ImageFormat::Z_PIXMAP, pixmap, x, y, width, height, u32::MAX)?
+    .reply();
+  conn.free_pixmap(pixmap)?;
+
+  let mut data = img?.data;
+  for chunk in data.chunks_mut(4) {
+    // X11 gives BGRA, we need RGBA
+    chunk.swap(0, 2);
+    // Only 32-bit windows have an alpha channel; otherwise the byte is padding.
+    if depth < 32 {
+      chunk[3] = 255;
+    }
+  }
+
+  RgbaImage::from_raw(width.into(), height.into(), data)
+    .ok_or(X11ApiCaptureWindowImageError::InvalidBitmap)
+}
+
+/// Returns `None` while a newly redirected window is still being repainted.
+fn capture_window_image_with_composite(
+  conn: &RustConnection,
+  root: Window,
+  window: Window,
+  composite_state: &mut CompositeState,
+) -> Result<Option<RgbaImage>, X11ApiCaptureWindowImageError> {
+  let geom = conn.get_geometry(window)?.reply()?;
+  let size = (geom.width, geom.height);
+
+  // A compositing window manager already keeps frames redirected, so the
+  // window can be cropped from its frame's pixmap without redirecting it.
+  let frame = get_window_frame(conn, root, window)
+    .map_err(|e| X11ApiCaptureWindowImageError::Generic(format!("{:?}", e)))?;
+  if frame != window {
+    let offset = conn.translate_coordinates(window, frame, 0, 0)?.reply()?;
+    if let Ok(img) = read_window_pixmap(conn, frame, (offset.dst_x, offset.dst_y), size) {
+      return Ok(Some(img));
+    }
+  }
+
+  if !composite_state.redirected.contains(&window) {
+    redirect_window(conn, window, composite_state)?;
+  }
+
+  if let Some(repaint) = composite_state.repainting.get(&window) {
+    if !repaint.is_settled(Instant::now()) {
+      return Ok(None);
+    }
+    conn.damage_destroy(repaint.damage)?;
+    composite_state.repainting.remove(&window);
+  }
+
+  read_window_pixmap(conn, window, (0, 0), size).map(Some)
+}
+
 static X11_API_SENDER: OnceCell<Sender<(X11ApiCommand, Sender<X11ApiResponse>)>> = OnceCell::new();
 static INIT_X11_API_THREAD: Once = Once::new();
 
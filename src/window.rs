@@ -1,6 +1,11 @@
 use napi::{bindgen_prelude::AsyncTask, Env, Error, Task};
+use regex::Regex;
+use std::{
+  thread,
+  time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use crate::image::Image;
+use crate::image::{CaptureInfo, Image};
 
 #[cfg(target_os = "windows")]
 mod windows_backend;
@@ -32,6 +37,7 @@ impl From<WindowError> for Error {
 
 pub trait NativeWindow {
   fn box_clone(&self) -> Box<dyn NativeWindow + Send + Sync>;
+  fn id(&self) -> i64;
   fn title(&self) -> Result<String, WindowError>;
   fn x(&self) -> Result<i32, WindowError>;
   fn y(&self) -> Result<i32, WindowError>;
@@ -39,12 +45,229 @@ pub trait NativeWindow {
   fn height(&self) -> Result<u32, WindowError>;
   fn is_focused(&self) -> Result<bool, WindowError>;
   fn capture_image(&self) -> Result<image::RgbaImage, WindowError>;
+
+  /// The top-level window that decorates this one, which is the window itself
+  /// on platforms or window managers without separate frames.
+  fn frame(&self) -> Result<Box<dyn NativeWindow + Send + Sync>, WindowError> {
+    Ok(self.box_clone())
+  }
+
+  fn class(&self) -> Result<Option<WindowClass>, WindowError> {
+    Err(unsupported_property("class"))
+  }
+
+  fn pid(&self) -> Result<Option<u32>, WindowError> {
+    Err(unsupported_property("pid"))
+  }
+
+  fn process_path(&self) -> Result<Option<String>, WindowError> {
+    Err(unsupported_property("process path"))
+  }
+
+  fn role(&self) -> Result<Option<String>, WindowError> {
+    Err(unsupported_property("role"))
+  }
+
+  fn window_type(&self) -> Result<Vec<String>, WindowError> {
+    Err(unsupported_property("type"))
+  }
+
+  /// Requests focus without waiting for the window manager to grant it.
+  fn activate(&self) -> Result<(), WindowError> {
+    Err(unsupported_action("Activating"))
+  }
+
+  /// Focuses and raises the window directly, for when the window manager
+  /// doesn't grant `activate`. Like `activate`, it doesn't wait, and may need
+  /// repeating until the window is ready to take focus.
+  fn focus(&self) -> Result<(), WindowError> {
+    self.activate()
+  }
+
+  fn raise(&self) -> Result<(), WindowError> {
+    Err(unsupported_action("Raising"))
+  }
+
+  fn lower(&self) -> Result<(), WindowError> {
+    Err(unsupported_action("Lowering"))
+  }
+
+  fn state(&self) -> Result<WindowState, WindowError> {
+    Err(unsupported_property("state"))
+  }
+
+  fn exists(&self) -> Result<bool, WindowError> {
+    Err(unsupported_property("existence"))
+  }
+
+  /// Requests new bounds without waiting for the window manager to apply them.
+  fn set_bounds(&self, _bounds: WindowBounds) -> Result<(), WindowError> {
+    Err(unsupported_action("Moving and resizing"))
+  }
+
+  fn set_state(&self, _change: WindowStateChange) -> Result<(), WindowError> {
+    Err(unsupported_action("Changing the state of"))
+  }
+
+  fn close(&self) -> Result<(), WindowError> {
+    Err(unsupported_action("Closing"))
+  }
+}
+
+fn unsupported_action(action: &str) -> WindowError {
+  WindowError::from_reason(format!(
+    "{} windows is not supported on this platform",
+    action
+  ))
+}
+
+fn unsupported_property(property: &str) -> WindowError {
+  WindowError::from_reason(format!(
+    "Reading the window {} is not supported on this platform",
+    property
+  ))
+}
+
+#[napi(object)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WindowState {
+  pub minimized: bool,
+  pub maximized: bool,
+  pub fullscreen: bool,
+  pub always_on_top: bool,
+}
+
+/// Position and size of a window, in screen pixels. Omitted fields are left
+/// unchanged.
+#[napi(object)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WindowBounds {
+  pub x: Option<i32>,
+  pub y: Option<i32>,
+  pub width: Option<u32>,
+  pub height: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowStateChange {
+  Minimize,
+  Maximize,
+  /// Undoes minimising, maximising and fullscreen.
+  Restore,
+  Fullscreen(bool),
+  AlwaysOnTop(bool),
+}
+
+/// The two halves of X11's `WM_CLASS` property.
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct WindowClass {
+  /// Usually the executable name, or the value of `-name`.
+  pub instance: String,
+  /// The application's class, shared by all of its windows.
+  pub class_name: String,
 }
 
 pub trait NativeWindowFactory {
   fn all_windows() -> Result<Vec<Window>, WindowError>
   where
     Self: Sized;
+
+  fn from_id(_id: i64) -> Result<Window, WindowError>
+  where
+    Self: Sized,
+  {
+    Err(WindowError::from_reason(
+      "Looking up windows by ID is not supported on this platform",
+    ))
+  }
+
+  /// Backends that can read window classes and processes override this to
+  /// filter on their API thread; the default only understands titles.
+  fn find_windows(filter: WindowFilter) -> Result<Vec<Window>, WindowError>
+  where
+    Self: Sized,
+  {
+    if filter.class_name.is_some() || filter.pid.is_some() || filter.process_name.is_some() {
+      return Err(WindowError::from_reason(
+        "Finding windows by class name, PID or process name is not supported on this platform",
+      ));
+    }
+
+    Ok(
+      Self::all_windows()?
+        .into_iter()
+        .filter(|window| {
+          window
+            .native_window
+            .title()
+            .is_ok_and(|title| filter.matches_title(&title))
+        })
+        .collect(),
+    )
+  }
+}
+
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct WindowQuery {
+  /// Matches windows whose title contains this text.
+  pub title: Option<String>,
+  /// Matches windows whose title matches this regular expression.
+  pub title_regex: Option<String>,
+  /// Matches either part of `WM_CLASS`, the instance or the class name.
+  pub class_name: Option<String>,
+  pub pid: Option<u32>,
+  /// Matches the executable's file name, or the kernel's short process name.
+  pub process_name: Option<String>,
+}
+
+/// A `WindowQuery` with its regular expression compiled, ready to be sent to a
+/// backend's API thread. Every criterion given must match.
+#[derive(Clone, Debug, Default)]
+pub struct WindowFilter {
+  pub title: Option<String>,
+  pub title_regex: Option<Regex>,
+  pub class_name: Option<String>,
+  pub pid: Option<u32>,
+  pub process_name: Option<String>,
+}
+
+impl WindowFilter {
+  pub fn needs_title(&self) -> bool {
+    self.title.is_some() || self.title_regex.is_some()
+  }
+
+  pub fn matches_title(&self, title: &str) -> bool {
+    self
+      .title
+      .as_ref()
+      .is_none_or(|text| title.contains(text.as_str()))
+      && self
+        .title_regex
+        .as_ref()
+        .is_none_or(|regex| regex.is_match(title))
+  }
+}
+
+impl TryFrom<WindowQuery> for WindowFilter {
+  type Error = WindowError;
+
+  fn try_from(query: WindowQuery) -> Result<Self, Self::Error> {
+    let title_regex = query
+      .title_regex
+      .map(|pattern| Regex::new(&pattern))
+      .transpose()
+      .map_err(|e| WindowError::from_reason(format!("Invalid title regex: {}", e)))?;
+
+    Ok(WindowFilter {
+      title: query.title,
+      title_regex,
+      class_name: query.class_name,
+      pid: query.pid,
+      process_name: query.process_name,
+    })
+  }
 }
 
 #[napi]
@@ -83,6 +306,65 @@ impl Window {
     }
   }
 
+  /// The window with the native handle returned by `id()`, for example from a
+  /// `WindowListener` event. The window isn't checked to exist.
+  #[napi]
+  pub fn from_id(id: i64) -> Result<Window, Error> {
+    #[cfg(target_os = "windows")]
+    {
+      Ok(WindowsWindow::from_id(id)?)
+    }
+    #[cfg(target_os = "linux")]
+    {
+      Ok(X11Window::from_id(id)?)
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    {
+      Ok(unsupported_backend::UnsupportedOSWindow::from_id(id)?)
+    }
+  }
+
+  /// Returns the windows matching every criterion in `query`, filtered
+  /// natively rather than by querying each window from JavaScript.
+  #[napi]
+  pub fn find(query: WindowQuery) -> Result<Vec<Window>, Error> {
+    let filter = WindowFilter::try_from(query)?;
+    #[cfg(target_os = "windows")]
+    {
+      Ok(WindowsWindow::find_windows(filter)?)
+    }
+    #[cfg(target_os = "linux")]
+    {
+      Ok(X11Window::find_windows(filter)?)
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    {
+      Ok(unsupported_backend::UnsupportedOSWindow::find_windows(
+        filter,
+      )?)
+    }
+  }
+
+  /// Like `find`, but returns only the first match.
+  #[napi]
+  pub fn find_one(query: WindowQuery) -> Result<Option<Window>, Error> {
+    Ok(Window::find(query)?.into_iter().next())
+  }
+
+  fn change(&self, change: WindowChange, timeout: Option<u32>) -> AsyncTask<AsyncChangeWindow> {
+    AsyncTask::new(AsyncChangeWindow::new(
+      self.clone(),
+      change,
+      Duration::from_millis(timeout.unwrap_or(1000).into()),
+    ))
+  }
+
+  /// Native handle of the window: the X11 window id or the Win32 `HWND`.
+  #[napi]
+  pub fn id(&self) -> i64 {
+    self.native_window.id()
+  }
+
   #[napi]
   pub fn title(&self) -> Result<String, Error> {
     Ok(self.native_window.title()?)
@@ -113,6 +395,176 @@ impl Window {
     Ok(self.native_window.is_focused()?)
   }
 
+  /// The frame a reparenting window manager wrapped this window in, including
+  /// its decorations. `id()` on the result is the frame's handle, while `id()`
+  /// on the windows returned by `all()` and `find()` is the application's.
+  #[napi]
+  pub fn frame(&self) -> Result<Window, Error> {
+    Ok(Window {
+      native_window: self.native_window.frame()?,
+    })
+  }
+
+  /// `WM_CLASS` on X11, or null when the window doesn't set it.
+  #[napi]
+  pub fn class(&self) -> Result<Option<WindowClass>, Error> {
+    Ok(self.native_window.class()?)
+  }
+
+  /// ID of the owning process as reported by `_NET_WM_PID`, or null when the
+  /// application doesn't set it.
+  #[napi]
+  pub fn pid(&self) -> Result<Option<u32>, Error> {
+    Ok(self.native_window.pid()?)
+  }
+
+  /// Path to the owning process' executable, or null when the PID is unknown
+  /// or the executable can't be read.
+  #[napi]
+  pub fn process_path(&self) -> Result<Option<String>, Error> {
+    Ok(self.native_window.process_path()?)
+  }
+
+  /// `WM_WINDOW_ROLE` on X11, which some toolkits set to tell apart windows
+  /// of the same class.
+  #[napi]
+  pub fn role(&self) -> Result<Option<String>, Error> {
+    Ok(self.native_window.role()?)
+  }
+
+  /// Types from `_NET_WM_WINDOW_TYPE` without their prefix, such as `normal`
+  /// or `dialog`, most specific first.
+  #[napi]
+  pub fn window_type(&self) -> Result<Vec<String>, Error> {
+    Ok(self.native_window.window_type()?)
+  }
+
+  /// Brings the window to the front and gives it keyboard focus, resolving
+  /// once it reports being focused, or to `false` if that doesn't happen
+  /// within `timeout` milliseconds. Defaults to 1000. If the window manager
+  /// hasn't focused the window halfway through, it is focused directly.
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn activate(&self, timeout: Option<u32>) -> AsyncTask<AsyncChangeWindow> {
+    self.change(WindowChange::Activate, timeout)
+  }
+
+  /// Moves the window above its siblings without focusing it.
+  #[napi]
+  pub fn raise(&self) -> Result<(), Error> {
+    Ok(self.native_window.raise()?)
+  }
+
+  /// Moves the window below its siblings.
+  #[napi]
+  pub fn lower(&self) -> Result<(), Error> {
+    Ok(self.native_window.lower()?)
+  }
+
+  #[napi]
+  pub fn state(&self) -> Result<WindowState, Error> {
+    Ok(self.native_window.state()?)
+  }
+
+  /// Whether the window is still open.
+  #[napi]
+  pub fn exists(&self) -> Result<bool, Error> {
+    Ok(self.native_window.exists()?)
+  }
+
+  /// Moves the window's top-left corner, resolving to whether the window
+  /// manager applied it within `timeout` milliseconds. Defaults to 1000.
+  /// Window managers may place either the window or its frame at the position.
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn move_to(&self, x: i32, y: i32, timeout: Option<u32>) -> AsyncTask<AsyncChangeWindow> {
+    self.set_bounds(
+      WindowBounds {
+        x: Some(x),
+        y: Some(y),
+        ..Default::default()
+      },
+      timeout,
+    )
+  }
+
+  /// Resizes the window, excluding its frame, resolving to whether the window
+  /// manager applied it within `timeout` milliseconds. Defaults to 1000.
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn resize_to(
+    &self,
+    width: u32,
+    height: u32,
+    timeout: Option<u32>,
+  ) -> AsyncTask<AsyncChangeWindow> {
+    self.set_bounds(
+      WindowBounds {
+        width: Some(width),
+        height: Some(height),
+        ..Default::default()
+      },
+      timeout,
+    )
+  }
+
+  /// Moves and resizes the window in one request, resolving to whether the
+  /// window manager applied it within `timeout` milliseconds. Defaults to 1000.
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn set_bounds(
+    &self,
+    bounds: WindowBounds,
+    timeout: Option<u32>,
+  ) -> AsyncTask<AsyncChangeWindow> {
+    self.change(WindowChange::Bounds(bounds), timeout)
+  }
+
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn minimize(&self, timeout: Option<u32>) -> AsyncTask<AsyncChangeWindow> {
+    self.change(WindowChange::State(WindowStateChange::Minimize), timeout)
+  }
+
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn maximize(&self, timeout: Option<u32>) -> AsyncTask<AsyncChangeWindow> {
+    self.change(WindowChange::State(WindowStateChange::Maximize), timeout)
+  }
+
+  /// Undoes minimising, maximising and fullscreen.
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn restore(&self, timeout: Option<u32>) -> AsyncTask<AsyncChangeWindow> {
+    self.change(WindowChange::State(WindowStateChange::Restore), timeout)
+  }
+
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn set_fullscreen(
+    &self,
+    fullscreen: bool,
+    timeout: Option<u32>,
+  ) -> AsyncTask<AsyncChangeWindow> {
+    self.change(
+      WindowChange::State(WindowStateChange::Fullscreen(fullscreen)),
+      timeout,
+    )
+  }
+
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn set_always_on_top(
+    &self,
+    always_on_top: bool,
+    timeout: Option<u32>,
+  ) -> AsyncTask<AsyncChangeWindow> {
+    self.change(
+      WindowChange::State(WindowStateChange::AlwaysOnTop(always_on_top)),
+      timeout,
+    )
+  }
+
+  /// Asks the application to close the window, resolving to whether it closed
+  /// within `timeout` milliseconds. Defaults to 1000. Applications may keep it
+  /// open, for example to ask about unsaved changes. On X11, clients that don't
+  /// support `WM_DELETE_WINDOW` are disconnected instead.
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn close(&self, timeout: Option<u32>) -> AsyncTask<AsyncChangeWindow> {
+    self.change(WindowChange::Close, timeout)
+  }
+
   #[napi(ts_return_type = "Promise<Image>")]
   pub fn capture_image(&self) -> AsyncTask<AsyncCaptureImage> {
     AsyncTask::new(AsyncCaptureImage::new(self.clone()))
@@ -135,8 +587,145 @@ impl Task for AsyncCaptureImage {
   type JsValue = Image;
 
   fn compute(&mut self) -> Result<Self::Output, Error> {
-    let rgba_image = self.window.native_window.capture_image()?;
-    Ok(Image::from(rgba_image))
+    let native_window = &self.window.native_window;
+    let screen_x = native_window.x()?;
+    let screen_y = native_window.y()?;
+    let rgba_image = native_window.capture_image()?;
+
+    Ok(
+      Image::from(rgba_image).with_capture_info(CaptureInfo {
+        timestamp: SystemTime::now()
+          .duration_since(UNIX_EPOCH)
+          .map(|duration| duration.as_secs_f64() * 1000.0)
+          .unwrap_or(0.0),
+        window_id: native_window.id(),
+        screen_x,
+        screen_y,
+      }),
+    )
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
+    Ok(output)
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum WindowChange {
+  Activate,
+  Bounds(WindowBounds),
+  State(WindowStateChange),
+  Close,
+}
+
+/// Whether the window, or the frame a window manager placed at the requested
+/// position, has its top-left corner there.
+fn position_matches(
+  window: &(dyn NativeWindow + Send + Sync),
+  bounds: &WindowBounds,
+) -> Result<bool, WindowError> {
+  let (x, y) = (window.x()?, window.y()?);
+  Ok(bounds.x.is_none_or(|target| target == x) && bounds.y.is_none_or(|target| target == y))
+}
+
+impl WindowChange {
+  fn apply(&self, window: &(dyn NativeWindow + Send + Sync)) -> Result<(), WindowError> {
+    match *self {
+      WindowChange::Activate => window.activate(),
+      WindowChange::Bounds(bounds) => window.set_bounds(bounds),
+      WindowChange::State(change) => window.set_state(change),
+      WindowChange::Close => window.close(),
+    }
+  }
+
+  /// Tries a more direct route once the request has had half the timeout to
+  /// take effect, for window managers that refuse it.
+  fn retry(&self, window: &(dyn NativeWindow + Send + Sync)) -> Result<(), WindowError> {
+    match *self {
+      WindowChange::Activate => window.focus(),
+      _ => Ok(()),
+    }
+  }
+
+  fn is_applied(&self, window: &(dyn NativeWindow + Send + Sync)) -> Result<bool, WindowError> {
+    match *self {
+      WindowChange::Activate => window.is_focused(),
+      WindowChange::Bounds(bounds) => {
+        if let Some(width) = bounds.width {
+          if window.width()? != width {
+            return Ok(false);
+          }
+        }
+        if let Some(height) = bounds.height {
+          if window.height()? != height {
+            return Ok(false);
+          }
+        }
+        if bounds.x.is_none() && bounds.y.is_none() {
+          return Ok(true);
+        }
+        Ok(
+          position_matches(window, &bounds)?
+            || position_matches(window.frame()?.as_ref(), &bounds)?,
+        )
+      }
+      WindowChange::State(change) => {
+        let state = window.state()?;
+        Ok(match change {
+          WindowStateChange::Minimize => state.minimized,
+          WindowStateChange::Maximize => state.maximized,
+          WindowStateChange::Restore => !state.minimized && !state.maximized && !state.fullscreen,
+          WindowStateChange::Fullscreen(fullscreen) => state.fullscreen == fullscreen,
+          WindowStateChange::AlwaysOnTop(always_on_top) => state.always_on_top == always_on_top,
+        })
+      }
+      WindowChange::Close => Ok(!window.exists()?),
+    }
+  }
+}
+
+/// Requests a change from the window manager, then polls until it shows up or
+/// the timeout expires, resolving to whether it did.
+pub struct AsyncChangeWindow {
+  window: Window,
+  change: WindowChange,
+  timeout: Duration,
+}
+
+impl AsyncChangeWindow {
+  pub fn new(window: Window, change: WindowChange, timeout: Duration) -> Self {
+    Self {
+      window,
+      change,
+      timeout,
+    }
+  }
+}
+
+#[napi]
+impl Task for AsyncChangeWindow {
+  type Output = bool;
+  type JsValue = bool;
+
+  fn compute(&mut self) -> Result<Self::Output, Error> {
+    let native_window = self.window.native_window.as_ref();
+    self.change.apply(native_window)?;
+
+    let start = Instant::now();
+    let (retry_at, deadline) = (start + self.timeout / 2, start + self.timeout);
+    loop {
+      if self.change.is_applied(native_window)? {
+        return Ok(true);
+      }
+      let now = Instant::now();
+      if now >= deadline {
+        return Ok(false);
+      }
+      if now >= retry_at {
+        self.change.retry(native_window)?;
+      }
+      thread::sleep(Duration::from_millis(10));
+    }
   }
 
   fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue, Error> {
@@ -7,6 +7,10 @@ impl NativeWindow for UnsupportedOSWindow {
     Box::new(UnsupportedOSWindow)
   }
 
+  fn id(&self) -> i64 {
+    0
+  }
+
   fn title(&self) -> Result<String, WindowError> {
     Err(WindowError::UnsupportedPlatform)
   }
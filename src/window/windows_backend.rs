@@ -126,6 +126,10 @@ impl NativeWindow for WindowsWindow {
     Box::new(self.clone())
   }
 
+  fn id(&self) -> i64 {
+    self.handle.as_hwnd().0 as i64
+  }
+
   fn title(&self) -> Result<String, WindowError> {
     match send_command_to_api_thread(WindowsApiCommand::GetWindowTitle(self.handle)).map_err(|e| WindowsNativeWindowTitleError::ApiError(e).into())? {
       WindowsApiResponse::WindowTitle(title) => Ok(title),
@@ -212,4 +216,16 @@ impl NativeWindowFactory for WindowsWindow {
       _ => Err(WindowsNativeWindowAllWindowsError::UnexpectedResponse.into()),
     }
   }
+
+  fn from_id(id: i64) -> Result<Window, WindowError>
+  where
+    Self: Sized,
+  {
+    Ok(
+      WindowsWindow {
+        handle: WindowHandle::from_id(id),
+      }
+      .into(),
+    )
+  }
 }
@@ -2,10 +2,17 @@
 
 use crate::native_api::x11_backend::{
   send_command_to_api_thread, WindowHandle, X11ApiCaptureWindowImageError, X11ApiCommand,
-  X11ApiEnumerateWindowsError, X11ApiError, X11ApiGetWindowRectError, X11ApiGetWindowTitleError,
-  X11ApiIsWindowFocusedError, X11ApiResponse, X11SendCommandToApiThreadError,
+  X11ApiEnumerateWindowsError, X11ApiError, X11ApiFindWindowsError, X11ApiGetWindowFrameError,
+  X11ApiGetWindowPropertyError, X11ApiGetWindowRectError, X11ApiGetWindowTitleError,
+  X11ApiWindowActionError, X11ApiIsWindowFocusedError, X11ApiResponse,
+  X11SendCommandToApiThreadError,
 };
-use crate::window::{NativeWindow, NativeWindowFactory, Window, WindowError};
+use crate::window::{
+  NativeWindow, NativeWindowFactory, Window, WindowBounds, WindowClass, WindowError, WindowFilter,
+  WindowState, WindowStateChange,
+};
+use std::thread;
+use std::time::Duration;
 
 #[derive(Clone, Debug)]
 #[allow(dead_code)]
@@ -119,6 +126,68 @@ impl Into<WindowError> for X11NativeWindowAllWindowsError {
   }
 }
 
+#[derive(Clone, Debug)]
+#[allow(dead_code)]
+pub enum X11NativeWindowFindWindowsError {
+  ApiError(X11SendCommandToApiThreadError),
+  FindWindowsError(X11ApiFindWindowsError),
+  UnexpectedResponse,
+}
+
+// Implemented as `Into`, like the impls above: any `From<_> for WindowError`
+// impl would make their `.map_err(|e| ….into())?` calls ambiguous.
+#[allow(clippy::from_over_into)]
+impl Into<WindowError> for X11NativeWindowFindWindowsError {
+  fn into(self) -> WindowError {
+    WindowError::from_reason(format!("{:?}", self))
+  }
+}
+
+#[derive(Clone, Debug)]
+#[allow(dead_code)]
+pub enum X11NativeWindowPropertyError {
+  ApiError(X11SendCommandToApiThreadError),
+  GetWindowPropertyError(X11ApiGetWindowPropertyError),
+  UnexpectedResponse,
+}
+
+#[allow(clippy::from_over_into)]
+impl Into<WindowError> for X11NativeWindowPropertyError {
+  fn into(self) -> WindowError {
+    WindowError::from_reason(format!("{:?}", self))
+  }
+}
+
+#[derive(Clone, Debug)]
+#[allow(dead_code)]
+pub enum X11NativeWindowFrameError {
+  ApiError(X11SendCommandToApiThreadError),
+  GetWindowFrameError(X11ApiGetWindowFrameError),
+  UnexpectedResponse,
+}
+
+#[allow(clippy::from_over_into)]
+impl Into<WindowError> for X11NativeWindowFrameError {
+  fn into(self) -> WindowError {
+    WindowError::from_reason(format!("{:?}", self))
+  }
+}
+
+#[derive(Clone, Debug)]
+#[allow(dead_code)]
+pub enum X11NativeWindowActionError {
+  ApiError(X11SendCommandToApiThreadError),
+  WindowActionError(X11ApiWindowActionError),
+  UnexpectedResponse,
+}
+
+#[allow(clippy::from_over_into)]
+impl Into<WindowError> for X11NativeWindowActionError {
+  fn into(self) -> WindowError {
+    WindowError::from_reason(format!("{:?}", self))
+  }
+}
+
 pub struct X11Window {
   handle: WindowHandle,
 }
@@ -139,11 +208,25 @@ impl Into<Window> for X11Window {
   }
 }
 
+impl X11Window {
+  fn send_action(&self, command: X11ApiCommand) -> Result<(), WindowError> {
+    match send_command_to_api_thread(command).map_err(|e| X11NativeWindowActionError::ApiError(e).into())? {
+      X11ApiResponse::Acknowledgement => Ok(()),
+      X11ApiResponse::Error(X11ApiError::WindowAction(e)) => Err(X11NativeWindowActionError::WindowActionError(e).into()),
+      _ => Err(X11NativeWindowActionError::UnexpectedResponse.into()),
+    }
+  }
+}
+
 impl NativeWindow for X11Window {
   fn box_clone(&self) -> Box<dyn NativeWindow + Send + Sync> {
     Box::new(self.clone())
   }
 
+  fn id(&self) -> i64 {
+    self.handle.as_window().into()
+  }
+
   fn title(&self) -> Result<String, WindowError> {
     match send_command_to_api_thread(X11ApiCommand::GetWindowTitle(self.handle)).map_err(|e| X11NativeWindowTitleError::ApiError(e).into())? {
       X11ApiResponse::WindowTitle(title) => Ok(title),
@@ -195,15 +278,121 @@ impl NativeWindow for X11Window {
   }
 
   fn capture_image(&self) -> Result<image::RgbaImage, WindowError> {
-    match send_command_to_api_thread(X11ApiCommand::CaptureWindowImage(self.handle)).map_err(|e| X11NativeWindowCaptureImageError::ApiError(e).into())? {
-      X11ApiResponse::WindowImage(img) => Ok(img),
-      X11ApiResponse::Error(X11ApiError::CaptureWindowImage(e)) => Err(X11NativeWindowCaptureImageError::CaptureWindowImageError(e).into()),
-      _ => Err(X11NativeWindowCaptureImageError::UnexpectedResponse.into()),
+    // A window captured for the first time may still be repainting, which the
+    // API thread reports instead of waiting so that other calls aren't held up.
+    loop {
+      match send_command_to_api_thread(X11ApiCommand::CaptureWindowImage(self.handle)).map_err(|e| X11NativeWindowCaptureImageError::ApiError(e).into())? {
+        X11ApiResponse::WindowImage(img) => return Ok(img),
+        X11ApiResponse::WindowRepainting => thread::sleep(Duration::from_millis(5)),
+        X11ApiResponse::Error(X11ApiError::CaptureWindowImage(e)) => return Err(X11NativeWindowCaptureImageError::CaptureWindowImageError(e).into()),
+        _ => return Err(X11NativeWindowCaptureImageError::UnexpectedResponse.into()),
+      }
+    }
+  }
+
+  fn frame(&self) -> Result<Box<dyn NativeWindow + Send + Sync>, WindowError> {
+    match send_command_to_api_thread(X11ApiCommand::GetWindowFrame(self.handle)).map_err(|e| X11NativeWindowFrameError::ApiError(e).into())? {
+      X11ApiResponse::WindowFrame(handle) => Ok(Box::new(X11Window { handle })),
+      X11ApiResponse::Error(X11ApiError::GetWindowFrame(e)) => Err(X11NativeWindowFrameError::GetWindowFrameError(e).into()),
+      _ => Err(X11NativeWindowFrameError::UnexpectedResponse.into()),
+    }
+  }
+
+  fn class(&self) -> Result<Option<WindowClass>, WindowError> {
+    match send_command_to_api_thread(X11ApiCommand::GetWindowClass(self.handle)).map_err(|e| X11NativeWindowPropertyError::ApiError(e).into())? {
+      X11ApiResponse::WindowClass(class) => Ok(class.map(|(instance, class_name)| WindowClass { instance, class_name })),
+      X11ApiResponse::Error(X11ApiError::GetWindowProperty(e)) => Err(X11NativeWindowPropertyError::GetWindowPropertyError(e).into()),
+      _ => Err(X11NativeWindowPropertyError::UnexpectedResponse.into()),
+    }
+  }
+
+  fn pid(&self) -> Result<Option<u32>, WindowError> {
+    match send_command_to_api_thread(X11ApiCommand::GetWindowPid(self.handle)).map_err(|e| X11NativeWindowPropertyError::ApiError(e).into())? {
+      X11ApiResponse::WindowPid(pid) => Ok(pid),
+      X11ApiResponse::Error(X11ApiError::GetWindowProperty(e)) => Err(X11NativeWindowPropertyError::GetWindowPropertyError(e).into()),
+      _ => Err(X11NativeWindowPropertyError::UnexpectedResponse.into()),
+    }
+  }
+
+  fn process_path(&self) -> Result<Option<String>, WindowError> {
+    match send_command_to_api_thread(X11ApiCommand::GetWindowProcessPath(self.handle)).map_err(|e| X11NativeWindowPropertyError::ApiError(e).into())? {
+      X11ApiResponse::WindowProcessPath(path) => Ok(path.map(|path| path.to_string_lossy().into_owned())),
+      X11ApiResponse::Error(X11ApiError::GetWindowProperty(e)) => Err(X11NativeWindowPropertyError::GetWindowPropertyError(e).into()),
+      _ => Err(X11NativeWindowPropertyError::UnexpectedResponse.into()),
+    }
+  }
+
+  fn role(&self) -> Result<Option<String>, WindowError> {
+    match send_command_to_api_thread(X11ApiCommand::GetWindowRole(self.handle)).map_err(|e| X11NativeWindowPropertyError::ApiError(e).into())? {
+      X11ApiResponse::WindowRole(role) => Ok(role),
+      X11ApiResponse::Error(X11ApiError::GetWindowProperty(e)) => Err(X11NativeWindowPropertyError::GetWindowPropertyError(e).into()),
+      _ => Err(X11NativeWindowPropertyError::UnexpectedResponse.into()),
     }
   }
+
+  fn window_type(&self) -> Result<Vec<String>, WindowError> {
+    match send_command_to_api_thread(X11ApiCommand::GetWindowType(self.handle)).map_err(|e| X11NativeWindowPropertyError::ApiError(e).into())? {
+      X11ApiResponse::WindowType(types) => Ok(types),
+      X11ApiResponse::Error(X11ApiError::GetWindowProperty(e)) => Err(X11NativeWindowPropertyError::GetWindowPropertyError(e).into()),
+      _ => Err(X11NativeWindowPropertyError::UnexpectedResponse.into()),
+    }
+  }
+
+  fn activate(&self) -> Result<(), WindowError> {
+    self.send_action(X11ApiCommand::ActivateWindow(self.handle))
+  }
+
+  fn focus(&self) -> Result<(), WindowError> {
+    self.send_action(X11ApiCommand::FocusWindow(self.handle))
+  }
+
+  fn raise(&self) -> Result<(), WindowError> {
+    self.send_action(X11ApiCommand::RaiseWindow(self.handle))
+  }
+
+  fn lower(&self) -> Result<(), WindowError> {
+    self.send_action(X11ApiCommand::LowerWindow(self.handle))
+  }
+
+  fn state(&self) -> Result<WindowState, WindowError> {
+    match send_command_to_api_thread(X11ApiCommand::GetWindowState(self.handle)).map_err(|e| X11NativeWindowPropertyError::ApiError(e).into())? {
+      X11ApiResponse::WindowState(state) => Ok(state),
+      X11ApiResponse::Error(X11ApiError::GetWindowProperty(e)) => Err(X11NativeWindowPropertyError::GetWindowPropertyError(e).into()),
+      _ => Err(X11NativeWindowPropertyError::UnexpectedResponse.into()),
+    }
+  }
+
+  fn exists(&self) -> Result<bool, WindowError> {
+    match send_command_to_api_thread(X11ApiCommand::WindowExists(self.handle)).map_err(|e| X11NativeWindowPropertyError::ApiError(e).into())? {
+      X11ApiResponse::WindowExists(exists) => Ok(exists),
+      X11ApiResponse::Error(X11ApiError::GetWindowProperty(e)) => Err(X11NativeWindowPropertyError::GetWindowPropertyError(e).into()),
+      _ => Err(X11NativeWindowPropertyError::UnexpectedResponse.into()),
+    }
+  }
+
+  fn set_bounds(&self, bounds: WindowBounds) -> Result<(), WindowError> {
+    self.send_action(X11ApiCommand::SetWindowBounds(self.handle, bounds))
+  }
+
+  fn set_state(&self, change: WindowStateChange) -> Result<(), WindowError> {
+    self.send_action(X11ApiCommand::SetWindowState(self.handle, change))
+  }
+
+  fn close(&self) -> Result<(), WindowError> {
+    self.send_action(X11ApiCommand::CloseWindow(self.handle))
+  }
 }
 
 impl NativeWindowFactory for X11Window {
+  fn from_id(id: i64) -> Result<Window, WindowError>
+  where
+    Self: Sized,
+  {
+    let window = u32::try_from(id)
+      .map_err(|_| WindowError::from_reason(format!("{} is not an X11 window ID", id)))?;
+    Ok(X11Window { handle: WindowHandle::new(window) }.into())
+  }
+
   fn all_windows() -> Result<Vec<Window>, WindowError>
   where
     Self: Sized,
@@ -220,4 +409,21 @@ impl NativeWindowFactory for X11Window {
       _ => Err(X11NativeWindowAllWindowsError::UnexpectedResponse.into()),
     }
   }
+
+  fn find_windows(filter: WindowFilter) -> Result<Vec<Window>, WindowError>
+  where
+    Self: Sized,
+  {
+    let response = send_command_to_api_thread(X11ApiCommand::FindWindows(filter)).map_err(|e| X11NativeWindowFindWindowsError::ApiError(e).into())?;
+    match response {
+      X11ApiResponse::WindowList(handles) => Ok(
+        handles
+          .into_iter()
+          .map(|handle| X11Window { handle }.into())
+          .collect(),
+      ),
+      X11ApiResponse::Error(X11ApiError::FindWindows(e)) => Err(X11NativeWindowFindWindowsError::FindWindowsError(e).into()),
+      _ => Err(X11NativeWindowFindWindowsError::UnexpectedResponse.into()),
+    }
+  }
 }
@@ -0,0 +1,192 @@
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, sync_channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+pub mod unsupported_backend;
+pub mod x11_backend;
+
+#[cfg(not(target_os = "linux"))]
+use unsupported_backend::ListenerWaker;
+#[cfg(target_os = "linux")]
+use x11_backend::ListenerWaker;
+
+/// A change to an application window. `windowId` matches `Window.id()`, and
+/// `Window.fromId()` turns it back into a window.
+#[napi]
+#[derive(Clone, Debug)]
+pub enum WindowEvent {
+  Created {
+    window_id: i64,
+  },
+  /// The window was destroyed, or withdrawn from the window manager.
+  Destroyed {
+    window_id: i64,
+  },
+  Mapped {
+    window_id: i64,
+  },
+  Unmapped {
+    window_id: i64,
+  },
+  /// The window moved or resized. Position is in screen coordinates.
+  BoundsChanged {
+    window_id: i64,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+  },
+  /// Another window, or none, became focused.
+  FocusChanged {
+    window_id: Option<i64>,
+  },
+  TitleChanged {
+    window_id: i64,
+    title: String,
+  },
+}
+
+type Subscriber = ThreadsafeFunction<WindowEvent>;
+type SubscriberId = u64;
+
+#[derive(Clone, Default)]
+struct ListenerState {
+  subscribers: Arc<Mutex<HashMap<SubscriberId, Subscriber>>>,
+  next_id: Arc<Mutex<SubscriberId>>,
+  closed: Arc<AtomicBool>,
+}
+
+impl ListenerState {
+  fn add_subscriber(&self, subscriber: Subscriber) -> SubscriberId {
+    let mut next_id_guard = self.next_id.lock().unwrap();
+    let id = *next_id_guard;
+    *next_id_guard += 1;
+
+    let mut subs_guard = self.subscribers.lock().unwrap();
+    subs_guard.insert(id, subscriber);
+    id
+  }
+
+  fn remove_subscriber(&self, id: SubscriberId) {
+    let mut subs_guard = self.subscribers.lock().unwrap();
+    subs_guard.remove(&id);
+  }
+
+  fn close(&self) {
+    self.closed.store(true, Ordering::SeqCst);
+    self.subscribers.lock().unwrap().clear();
+  }
+
+  fn broadcast(&self, event: WindowEvent) {
+    let subs_guard = self.subscribers.lock().unwrap();
+    for sub in subs_guard.values() {
+      sub.call(Ok(event.clone()), ThreadsafeFunctionCallMode::Blocking);
+    }
+  }
+}
+
+/// Reports windows being created, destroyed, shown, hidden, moved, focused
+/// and renamed, so callers don't need to poll `Window.all()`.
+///
+/// Whether a window manager is running is checked once, when the listener is
+/// created. If one starts later, create a new listener to follow its windows.
+#[napi]
+pub struct WindowListener {
+  state: ListenerState,
+  event_tx: Option<Sender<WindowEvent>>,
+  waker: Option<ListenerWaker>,
+  _os_listener_handle: Option<JoinHandle<()>>,
+  _dispatcher_handle: Option<JoinHandle<()>>,
+}
+
+#[napi]
+impl WindowListener {
+  #[napi(constructor)]
+  pub fn new() -> Result<Self> {
+    let state = ListenerState::default();
+    let (event_tx, event_rx) = channel::<WindowEvent>();
+
+    let dispatcher_state = state.clone();
+    let _dispatcher_handle = Some(thread::spawn(move || {
+      // The OS thread holds its own sender, so stop on the closed flag instead
+      // of waiting for every sender to drop. Dropping the receiver then makes
+      // the OS thread's next send fail, ending it too.
+      while let Ok(event) = event_rx.recv() {
+        if dispatcher_state.closed.load(Ordering::SeqCst) {
+          break;
+        }
+        dispatcher_state.broadcast(event);
+      }
+    }));
+
+    let os_listener_tx = event_tx.clone();
+    let (init_tx, init_rx) = sync_channel(1);
+
+    let _os_listener_handle = Some(thread::spawn(move || {
+      #[cfg(target_os = "linux")]
+      x11_backend::start_listener(os_listener_tx, init_tx);
+
+      #[cfg(not(target_os = "linux"))]
+      unsupported_backend::start_listener(os_listener_tx, init_tx);
+    }));
+
+    match init_rx.recv() {
+      Ok(Ok(waker)) => Ok(Self {
+        state,
+        event_tx: Some(event_tx),
+        waker: Some(waker),
+        _os_listener_handle,
+        _dispatcher_handle,
+      }),
+      Ok(Err(err_msg)) => Err(Error::from_reason(err_msg)),
+      Err(_) => Err(Error::from_reason(
+        "The window listener thread panicked during initialization.",
+      )),
+    }
+  }
+
+  #[napi]
+  pub fn subscribe<'a>(
+    &'a self,
+    env: &'a Env,
+    subscriber: ThreadsafeFunction<WindowEvent>,
+  ) -> Result<Function<'a, (), ()>> {
+    let id = self.state.add_subscriber(subscriber);
+    let state_clone = self.state.clone();
+
+    env.create_function_from_closure("unsubscribe", move |_ctx| {
+      state_clone.remove_subscriber(id);
+      Ok(())
+    })
+  }
+
+  /// Stops delivering events and waits for the listener's threads to exit.
+  #[napi]
+  pub fn close(&mut self) -> Result<()> {
+    self.state.close();
+    if let Some(waker) = self.waker.take() {
+      waker.wake();
+    }
+    if let Some(tx) = self.event_tx.take() {
+      drop(tx);
+    }
+    // Once both senders are gone, the dispatcher's `recv` fails and it exits.
+    if let Some(handle) = self._os_listener_handle.take() {
+      let _ = handle.join();
+    }
+    if let Some(handle) = self._dispatcher_handle.take() {
+      let _ = handle.join();
+    }
+    Ok(())
+  }
+}
+
+impl Drop for WindowListener {
+  fn drop(&mut self) {
+    let _ = self.close();
+  }
+}
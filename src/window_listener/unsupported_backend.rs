@@ -0,0 +1,17 @@
+#![cfg(not(target_os = "linux"))]
+
+use crate::window_listener::WindowEvent;
+use std::sync::mpsc::{Sender, SyncSender};
+
+pub struct ListenerWaker;
+
+impl ListenerWaker {
+  pub fn wake(&self) {}
+}
+
+pub fn start_listener(
+  _tx: Sender<WindowEvent>,
+  init_tx: SyncSender<Result<ListenerWaker, &'static str>>,
+) {
+  let _ = init_tx.send(Err("Window listener is not supported on this platform."));
+}
@@ -0,0 +1,438 @@
+#![cfg(target_os = "linux")]
+
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{Sender, SyncSender};
+use std::sync::Arc;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+  Atom, AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent, ConnectionExt, CreateWindowAux,
+  EventMask, InputFocus, NotifyMode, Window, WindowClass,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+use crate::window_listener::WindowEvent;
+
+struct ListenerAtoms {
+  net_client_list: Atom,
+  net_active_window: Atom,
+  net_wm_name: Atom,
+  utf8_string: Atom,
+  wake: Atom,
+}
+
+/// Wakes the listener thread from `wait_for_event` so it can exit. It sends a
+/// client message to an unmapped window the listener created; with an empty
+/// event mask, only the window's creator receives it.
+pub struct ListenerWaker {
+  conn: Arc<RustConnection>,
+  window: Window,
+  atom: Atom,
+}
+
+impl ListenerWaker {
+  pub fn wake(&self) {
+    let event = ClientMessageEvent::new(32, self.window, self.atom, [0u32; 5]);
+    let _ = self
+      .conn
+      .send_event(false, self.window, EventMask::NO_EVENT, event);
+    let _ = self.conn.flush();
+  }
+}
+
+/// Tracks application windows on a connection of its own, since the API
+/// thread's connection is only read in reply to commands.
+struct Listener {
+  conn: Arc<RustConnection>,
+  root: Window,
+  wake_window: Window,
+  atoms: ListenerAtoms,
+  tx: Sender<WindowEvent>,
+  /// Whether a window manager publishes `_NET_CLIENT_LIST`. Without one, the
+  /// root's top-level children are the application windows. Only checked at
+  /// startup, since a window manager starting later reparents every window.
+  managed: bool,
+  clients: HashSet<Window>,
+  bounds: HashMap<Window, (i32, i32, u32, u32)>,
+  focused: Option<Window>,
+}
+
+impl Listener {
+  /// Returns `false` once nobody is listening any more.
+  fn send(&self, event: WindowEvent) -> bool {
+    self.tx.send(event).is_ok()
+  }
+
+  fn client_list(&self) -> Option<Vec<Window>> {
+    let prop = self
+      .conn
+      .get_property(
+        false,
+        self.root,
+        self.atoms.net_client_list,
+        AtomEnum::WINDOW,
+        0,
+        u32::MAX,
+      )
+      .ok()?
+      .reply()
+      .ok()?;
+    let clients = prop.value32()?.collect();
+    Some(clients)
+  }
+
+  fn top_level_windows(&self) -> Vec<Window> {
+    let Some(tree) = self
+      .conn
+      .query_tree(self.root)
+      .ok()
+      .and_then(|cookie| cookie.reply().ok())
+    else {
+      return Vec::new();
+    };
+
+    // Menus and tooltips set override-redirect, and aren't application windows.
+    tree
+      .children
+      .into_iter()
+      .filter(|&window| {
+        self
+          .conn
+          .get_window_attributes(window)
+          .ok()
+          .and_then(|cookie| cookie.reply().ok())
+          .is_some_and(|attrs| !attrs.override_redirect)
+      })
+      .collect()
+  }
+
+  fn active_window(&self) -> Option<Window> {
+    let prop = self
+      .conn
+      .get_property(
+        false,
+        self.root,
+        self.atoms.net_active_window,
+        AtomEnum::WINDOW,
+        0,
+        1,
+      )
+      .ok()?
+      .reply()
+      .ok()?;
+    let active_window = prop.value32()?.next();
+    active_window.filter(|&window| window != x11rb::NONE)
+  }
+
+  /// Whether the input focus is on no window at all, rather than moving to
+  /// another one, which reports its own `FocusIn`.
+  fn focus_is_unset(&self) -> bool {
+    let Some(reply) = self
+      .conn
+      .get_input_focus()
+      .ok()
+      .and_then(|cookie| cookie.reply().ok())
+    else {
+      return false;
+    };
+    reply.focus == x11rb::NONE
+      || reply.focus == u32::from(InputFocus::POINTER_ROOT)
+      || reply.focus == self.root
+  }
+
+  fn title(&self, window: Window) -> String {
+    let read = |property: Atom, type_: Atom| {
+      self
+        .conn
+        .get_property(false, window, property, type_, 0, u32::MAX)
+        .ok()
+        .and_then(|cookie| cookie.reply().ok())
+        .map(|prop| prop.value)
+        .filter(|value| !value.is_empty())
+    };
+
+    read(self.atoms.net_wm_name, self.atoms.utf8_string)
+      .or_else(|| read(AtomEnum::WM_NAME.into(), AtomEnum::STRING.into()))
+      .map(|value| String::from_utf8_lossy(&value).into_owned())
+      .unwrap_or_default()
+  }
+
+  fn screen_bounds(&self, window: Window) -> Option<(i32, i32, u32, u32)> {
+    let geometry = self.conn.get_geometry(window).ok()?.reply().ok()?;
+    let origin = self
+      .conn
+      .translate_coordinates(window, self.root, 0, 0)
+      .ok()?
+      .reply()
+      .ok()?;
+    Some((
+      origin.dst_x.into(),
+      origin.dst_y.into(),
+      geometry.width.into(),
+      geometry.height.into(),
+    ))
+  }
+
+  /// Starts following a window's own events. With a window manager, the
+  /// root's substructure events describe frames, so the client's structure
+  /// events are selected too; without one they would arrive twice.
+  fn track(&mut self, window: Window) {
+    let event_mask = if self.managed {
+      EventMask::STRUCTURE_NOTIFY | EventMask::PROPERTY_CHANGE
+    } else {
+      EventMask::PROPERTY_CHANGE | EventMask::FOCUS_CHANGE
+    };
+    self
+      .conn
+      .change_window_attributes(
+        window,
+        &ChangeWindowAttributesAux::new().event_mask(event_mask),
+      )
+      .ok();
+
+    self.clients.insert(window);
+    if let Some(bounds) = self.screen_bounds(window) {
+      self.bounds.insert(window, bounds);
+    }
+  }
+
+  fn untrack(&mut self, window: Window) -> bool {
+    self.bounds.remove(&window);
+    self.clients.remove(&window)
+  }
+
+  fn sync_client_list(&mut self) -> bool {
+    let Some(client_list) = self.client_list() else {
+      return true;
+    };
+
+    let removed: Vec<Window> = self
+      .clients
+      .iter()
+      .copied()
+      .filter(|window| !client_list.contains(window))
+      .collect();
+    for window in removed {
+      self.untrack(window);
+      if !self.send(WindowEvent::Destroyed {
+        window_id: window.into(),
+      }) {
+        return false;
+      }
+    }
+
+    for window in client_list {
+      if !self.clients.contains(&window) {
+        self.track(window);
+        if !self.send(WindowEvent::Created {
+          window_id: window.into(),
+        }) {
+          return false;
+        }
+      }
+    }
+    true
+  }
+
+  fn set_focused(&mut self, window: Option<Window>) -> bool {
+    if self.focused == window {
+      return true;
+    }
+    self.focused = window;
+    self.send(WindowEvent::FocusChanged {
+      window_id: window.map(i64::from),
+    })
+  }
+
+  /// Returns `false` once nobody is listening any more.
+  fn handle(&mut self, event: Event) -> bool {
+    match event {
+      Event::ClientMessage(e) if e.window == self.wake_window && e.type_ == self.atoms.wake => {
+        false
+      }
+      Event::PropertyNotify(e) if e.window == self.root => {
+        if e.atom == self.atoms.net_client_list && self.managed {
+          self.sync_client_list()
+        } else if e.atom == self.atoms.net_active_window {
+          let active_window = self.active_window();
+          self.set_focused(active_window)
+        } else {
+          true
+        }
+      }
+      Event::PropertyNotify(e)
+        if self.clients.contains(&e.window)
+          && (e.atom == self.atoms.net_wm_name || e.atom == u32::from(AtomEnum::WM_NAME)) =>
+      {
+        self.send(WindowEvent::TitleChanged {
+          window_id: e.window.into(),
+          title: self.title(e.window),
+        })
+      }
+      Event::CreateNotify(e) if !self.managed && e.parent == self.root && !e.override_redirect => {
+        self.track(e.window);
+        self.send(WindowEvent::Created {
+          window_id: e.window.into(),
+        })
+      }
+      Event::DestroyNotify(e) if self.untrack(e.window) => self.send(WindowEvent::Destroyed {
+        window_id: e.window.into(),
+      }),
+      Event::MapNotify(e) if self.clients.contains(&e.window) => self.send(WindowEvent::Mapped {
+        window_id: e.window.into(),
+      }),
+      Event::UnmapNotify(e) if self.clients.contains(&e.window) => {
+        self.send(WindowEvent::Unmapped {
+          window_id: e.window.into(),
+        })
+      }
+      Event::ConfigureNotify(e) if self.clients.contains(&e.window) => {
+        // Restacking also sends ConfigureNotify, so only report real changes.
+        let Some(bounds) = self.screen_bounds(e.window) else {
+          return true;
+        };
+        if self.bounds.insert(e.window, bounds) == Some(bounds) {
+          return true;
+        }
+        let (x, y, width, height) = bounds;
+        self.send(WindowEvent::BoundsChanged {
+          window_id: e.window.into(),
+          x,
+          y,
+          width,
+          height,
+        })
+      }
+      Event::FocusIn(e)
+        if !self.managed && self.clients.contains(&e.event) && e.mode == NotifyMode::NORMAL =>
+      {
+        self.set_focused(Some(e.event))
+      }
+      Event::FocusOut(e)
+        if !self.managed
+          && self.focused == Some(e.event)
+          && e.mode == NotifyMode::NORMAL
+          && self.focus_is_unset() =>
+      {
+        self.set_focused(None)
+      }
+      _ => true,
+    }
+  }
+}
+
+pub fn start_listener(
+  tx: Sender<WindowEvent>,
+  init_tx: SyncSender<Result<ListenerWaker, &'static str>>,
+) {
+  let Ok((conn, screen_num)) = x11rb::connect(None) else {
+    let _ = init_tx.send(Err("Failed to connect to the X11 server."));
+    return;
+  };
+  let root = conn.setup().roots[screen_num].root;
+
+  let intern =
+    |name: &[u8]| -> Option<Atom> { Some(conn.intern_atom(false, name).ok()?.reply().ok()?.atom) };
+  let (
+    Some(net_client_list),
+    Some(net_active_window),
+    Some(net_wm_name),
+    Some(utf8_string),
+    Some(wake),
+  ) = (
+    intern(b"_NET_CLIENT_LIST"),
+    intern(b"_NET_ACTIVE_WINDOW"),
+    intern(b"_NET_WM_NAME"),
+    intern(b"UTF8_STRING"),
+    intern(b"_CRATE_WINDOW_LISTENER_WAKE"),
+  )
+  else {
+    let _ = init_tx.send(Err("Failed to intern X11 atoms."));
+    return;
+  };
+
+  let Some(wake_window) = conn.generate_id().ok().filter(|&window| {
+    conn
+      .create_window(
+        0,
+        window,
+        root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_ONLY,
+        x11rb::COPY_FROM_PARENT,
+        &CreateWindowAux::new(),
+      )
+      .ok()
+      .and_then(|cookie| cookie.check().ok())
+      .is_some()
+  }) else {
+    let _ = init_tx.send(Err("Failed to create the X11 listener window."));
+    return;
+  };
+
+  let root_events = ChangeWindowAttributesAux::new()
+    .event_mask(EventMask::SUBSTRUCTURE_NOTIFY | EventMask::PROPERTY_CHANGE);
+  if conn
+    .change_window_attributes(root, &root_events)
+    .map_err(|_| ())
+    .and_then(|cookie| cookie.check().map_err(|_| ()))
+    .is_err()
+  {
+    let _ = init_tx.send(Err("Failed to listen for events on the X11 root window."));
+    return;
+  }
+
+  let mut listener = Listener {
+    conn: Arc::new(conn),
+    root,
+    wake_window,
+    atoms: ListenerAtoms {
+      net_client_list,
+      net_active_window,
+      net_wm_name,
+      utf8_string,
+      wake,
+    },
+    tx,
+    managed: false,
+    clients: HashSet::new(),
+    bounds: HashMap::new(),
+    focused: None,
+  };
+
+  // Windows that already exist are tracked without reporting them as created.
+  let initial_clients = match listener.client_list() {
+    Some(client_list) => {
+      listener.managed = true;
+      client_list
+    }
+    None => listener.top_level_windows(),
+  };
+  for window in initial_clients {
+    listener.track(window);
+  }
+  listener.focused = listener.active_window();
+  if listener.conn.flush().is_err() {
+    let _ = init_tx.send(Err("Failed to listen for events on X11 windows."));
+    return;
+  }
+
+  let waker = ListenerWaker {
+    conn: listener.conn.clone(),
+    window: wake_window,
+    atom: wake,
+  };
+  if init_tx.send(Ok(waker)).is_err() {
+    return;
+  }
+
+  while let Ok(event) = listener.conn.wait_for_event() {
+    if !listener.handle(event) {
+      break;
+    }
+  }
+}
@@ -1,7 +1,12 @@
-import { strictEqual } from 'node:assert';
+import { rejects, strictEqual, throws } from 'node:assert';
+import { spawn, type ChildProcess } from 'node:child_process';
+import { existsSync, mkdtempSync } from 'node:fs';
+import { tmpdir } from 'node:os';
+import { join } from 'node:path';
 import { test } from 'node:test';
-import type { GlobalInputAction, GlobalInputActionType } from '../index.js';
-import { GlobalListener, Keyboard, Mouse, Position, SpecialKey, unicode, Window } from '../index.js';
+import { setTimeout as sleep } from 'node:timers/promises';
+import type { FeatureLocation, GlobalInputAction, GlobalInputActionType, Pixel } from '../index.js';
+import { BlendMode, compareHistograms, decodeFeature, encodeFeature, expectImageMatch, FeatureTracker, FillDirection, GlobalListener, HistogramComparison, Image, Keyboard, MotionDetector, Mouse, parseColour, Position, rgbaComponents, rgbaFromComponents, rgbaToHex, rgbaToHsv, SpecialKey, unicode, Window, WindowListener } from '../index.js';
 
 test('mouse move', async () => {
   const mouse = new Mouse();
@@ -87,6 +92,158 @@ test('screen capture', async () => {
   strictEqual(matchingPixels.some(p => p.x === target.x && p.y === target.y), true);
 });
 
+test('capturing a window repeatedly', async () => {
+  const window = Window.all().find(w => w.isFocused())!;
+  const size = `${window.width()}x${window.height()}`;
+
+  // The first capture may redirect the window and wait for it to repaint; the
+  // others, made at once, read the kept redirection.
+  const first = await window.captureImage();
+  const rest = await Promise.all([window.captureImage(), window.captureImage()]);
+  for (const image of [first, ...rest]) {
+    strictEqual(`${image.width}x${image.height}`, size);
+  }
+  strictEqual(await rest[0].getPixelRgba(0, 0), await rest[1].getPixelRgba(0, 0));
+});
+
+test('capture info maps image coordinates to the screen', async () => {
+  const window = Window.all().find(w => w.isFocused());
+  const image = await window!.captureImage();
+  const captureInfo = image.captureInfo();
+
+  strictEqual(captureInfo?.windowId, window!.id());
+  const screenPoint = image.toScreen(10, 20);
+  strictEqual(screenPoint.x, captureInfo!.screenX + 10);
+  strictEqual(screenPoint.y, captureInfo!.screenY + 20);
+  const imagePoint = image.fromScreen(screenPoint.x, screenPoint.y);
+  strictEqual(imagePoint.x, 10);
+  strictEqual(imagePoint.y, 20);
+});
+
+test('activating the focused window', async () => {
+  const window = Window.all().find(w => w.isFocused());
+
+  strictEqual(await window!.activate(500), true);
+  strictEqual(window!.isFocused(), true);
+});
+
+test('find windows by query', () => {
+  throws(() => Window.find({ titleRegex: '(' }), /Invalid title regex/);
+  throws(() => Window.findOne({ titleRegex: '[a-' }), /Invalid title regex/);
+
+  const window = Window.all().find(w => w.isFocused());
+  const title = window!.title();
+  const escaped = title.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
+
+  strictEqual(Window.find({ title }).some(w => w.id() === window!.id()), true);
+  strictEqual(Window.find({ titleRegex: `^${escaped}$` }).every(w => w.title() === title), true);
+  strictEqual(Window.findOne({ title, titleRegex: `^${escaped}$` })?.title(), title);
+  strictEqual(Window.findOne({ title: `${title} `, titleRegex: `^${escaped}$` }), null);
+});
+
+/** Opens an xterm to test window management on, if one is installed. */
+async function spawnTestWindow(): Promise<{ window: Window, child: ChildProcess } | undefined> {
+  const title = `herox-test-${process.pid}`;
+  const child = spawn('xterm', ['-name', 'herox-test', '-T', title, '-e', 'sleep 60'], { stdio: 'ignore' });
+  const spawned = await new Promise(resolve => {
+    child.once('spawn', () => resolve(true));
+    child.once('error', () => resolve(false));
+  });
+  if (!spawned) {
+    return undefined;
+  }
+
+  for (let attempt = 0; attempt < 100; attempt++) {
+    const window = Window.findOne({ title });
+    if (window) {
+      return { window, child };
+    }
+    await sleep(50);
+  }
+  child.kill();
+  return undefined;
+}
+
+test('spawned window properties', async t => {
+  const spawned = await spawnTestWindow();
+  if (!spawned) {
+    t.skip('xterm is not available');
+    return;
+  }
+  const { window, child } = spawned;
+
+  try {
+    const windowClass = window.class();
+    strictEqual(`${windowClass?.instance} ${windowClass?.className}`, 'herox-test XTerm');
+    strictEqual(Window.find({ className: 'herox-test' }).some(w => w.id() === window.id()), true);
+
+    const pid = window.pid();
+    if (pid !== null) {
+      strictEqual(pid, child.pid);
+      strictEqual(window.processPath()?.endsWith('xterm'), true);
+      strictEqual(Window.find({ pid }).some(w => w.id() === window.id()), true);
+    }
+  } finally {
+    child.kill();
+  }
+});
+
+test('moving and closing a spawned window', async t => {
+  const spawned = await spawnTestWindow();
+  if (!spawned) {
+    t.skip('xterm is not available');
+    return;
+  }
+  const { window, child } = spawned;
+
+  try {
+    // Window managers may place either the window or its frame at the position.
+    const position = () => [window, window.frame()].map(w => `${w.x()},${w.y()}`);
+
+    strictEqual(await window.moveTo(40, 50), true);
+    strictEqual(position().includes('40,50'), true);
+
+    strictEqual(await window.setBounds({ x: 60, y: 70 }), true);
+    strictEqual(position().includes('60,70'), true);
+
+    strictEqual(window.state().minimized, false);
+    strictEqual(window.state().fullscreen, false);
+
+    strictEqual(await window.close(), true);
+    strictEqual(window.exists(), false);
+  } finally {
+    child.kill();
+  }
+});
+
+test('window listener', async () => {
+  const listener = new WindowListener();
+  const created: Array<number> = [];
+  const unsubscribe = listener.subscribe((_err, event) => {
+    if (event.type === 'Created') {
+      created.push(event.windowId);
+    }
+  });
+
+  const spawned = await spawnTestWindow();
+  if (spawned) {
+    try {
+      const id = spawned.window.id();
+      for (let attempt = 0; attempt < 40 && !created.includes(id); attempt++) {
+        await sleep(50);
+      }
+      strictEqual(created.includes(id), true);
+    } finally {
+      spawned.child.kill();
+    }
+  }
+
+  unsubscribe();
+  // Closing waits for the listener's threads, so it must not hang.
+  listener.close();
+  listener.close();
+});
+
 test('get colour frequencies', async () => {
   const window = Window.all().find(w => w.isFocused());
   if (!window) {
@@ -116,3 +273,397 @@ test('get colour frequencies', async () => {
   strictEqual(Object.keys(expectedFrequencies).length, 0);
 });
 
+test('feature encoding round trip', () => {
+  const feature = {
+    pixels: [
+      { x: 2, y: 3, rgba: 0xff0000ff },
+      { x: 3, y: 3, rgba: 0xff0000ff },
+      { x: 5, y: 4, rgba: 0x00ff0080 },
+    ],
+  };
+
+  const decoded = decodeFeature(encodeFeature(feature));
+
+  strictEqual(decoded.pixels.length, feature.pixels.length);
+  for (const pixel of feature.pixels) {
+    strictEqual(decoded.pixels.some(p => p.x === pixel.x && p.y === pixel.y && p.rgba === pixel.rgba), true);
+  }
+});
+
+test('feature encoding rejects oversized bounds', () => {
+  const header = Buffer.alloc(22);
+  header.write('HXFT', 0, 'latin1');
+  header.writeUInt16LE(1, 4);
+  header.writeUInt32LE(0xffffffff, 14);
+  header.writeUInt32LE(0xffffffff, 18);
+  throws(() => decodeFeature(header));
+
+  throws(() =>
+    encodeFeature({
+      pixels: [
+        { x: 0, y: 0, rgba: 0xff0000ff },
+        { x: 100000, y: 100000, rgba: 0xff0000ff },
+      ],
+    }),
+  );
+});
+
+test('histogram comparison', () => {
+  const histogram = [0, 4, 8, 4, 0];
+  const scaled = histogram.map(count => count * 3);
+  const disjoint = [4, 0, 0, 0, 4];
+
+  strictEqual(compareHistograms(histogram, scaled, HistogramComparison.ChiSquare), 0);
+  strictEqual(compareHistograms(histogram, scaled, HistogramComparison.Intersection), 1);
+  strictEqual(compareHistograms(histogram, scaled, HistogramComparison.Bhattacharyya), 0);
+  strictEqual(compareHistograms(histogram, disjoint, HistogramComparison.Intersection), 0);
+  strictEqual(compareHistograms(histogram, disjoint, HistogramComparison.Bhattacharyya), 1);
+});
+
+test('image compositing', () => {
+  const background = Image.blank(4, 4, 0x808080ff);
+  const overlay = Image.blank(2, 2, 0xff000080);
+
+  background.paste(overlay, 1, 1);
+  strictEqual(background.getPixelRgbaSync(0, 0), 0x808080ff);
+  strictEqual(background.getPixelRgbaSync(1, 1), 0xc04040ff);
+
+  background.paste(overlay, 2, 2, { mode: BlendMode.Replace });
+  strictEqual(background.getPixelRgbaSync(3, 3), 0xff000080);
+
+  background.fill(0x00000000, { x: 0, y: 0, width: 1, height: 4 }, { mode: BlendMode.Replace });
+  strictEqual(background.getPixelRgbaSync(0, 3), 0);
+});
+
+test('compositing onto a transparent image', () => {
+  const image = Image.blank(2, 2);
+  image.paste(Image.blank(1, 1, 0xff000080), 0, 0);
+  strictEqual(image.getPixelRgbaSync(0, 0), 0xff000080);
+  strictEqual(image.getPixelRgbaSync(1, 1), 0);
+
+  throws(() => Image.blank(4294967295, 4294967295));
+});
+
+test('colour parsing', () => {
+  strictEqual(parseColour('#f80'), 0xff8800ff);
+  strictEqual(parseColour('#ff880080'), 0xff880080);
+  strictEqual(parseColour('rgb(255, 136, 0)'), 0xff8800ff);
+  strictEqual(parseColour('rgba(255 136 0 / 50%)'), 0xff880080);
+  strictEqual(parseColour('hsl(120, 100%, 50%)'), 0x00ff00ff);
+  strictEqual(rgbaFromComponents(255, 136, 0), 0xff8800ff);
+  strictEqual(rgbaComponents(0xff880080).alpha, 128);
+  strictEqual(rgbaToHex(0xff8800ff), '#ff8800ff');
+  strictEqual(rgbaToHsv(0x00ff00ff).hue, 120);
+  strictEqual(rgbaToHex('hsl(120, 100%, 50%)'), '#00ff00ff');
+  strictEqual(rgbaComponents('#ff880080').green, 136);
+  throws(() => parseColour('hsl(120, 1, 0.5)'));
+
+  const image = Image.blank(2, 2, '#000');
+  image.fill('rgb(255, 0, 0)');
+  strictEqual(image.getPixelRgbaSync(1, 1), 0xff0000ff);
+});
+
+test('drawing clips shapes running off the edge', () => {
+  const image = Image.blank(4, 4, 0x000000ff);
+
+  image.drawRectangle({ x: 2, y: 2, width: 4294967295, height: 4294967295 }, 0xff0000ff, { filled: true });
+  strictEqual(image.getPixelRgbaSync(1, 1), 0x000000ff);
+  strictEqual(image.getPixelRgbaSync(3, 3), 0xff0000ff);
+
+  image.drawCircle(0, 0, 4294967295, 0x00ff00ff, { filled: true });
+  strictEqual(image.getPixelRgbaSync(3, 3), 0x00ff00ff);
+
+  image.drawLine(-2000000000, 0, 2000000000, 0, 0x0000ffff, { thickness: 4294967295 });
+  strictEqual(image.getPixelRgbaSync(3, 3), 0x0000ffff);
+
+  image.drawText(-3, -3, 'X', 0xffffffff, { scale: 4294967295, background: 0x000000ff });
+  strictEqual(image.getPixelRgbaSync(3, 3), 0xffffffff);
+});
+
+test('detect circles', async () => {
+  const image = Image.blank(64, 48, 0x000000ff);
+  image.drawCircle(30, 22, 12, 0xffffffff, { filled: true });
+
+  const [circle] = await image.detectCircles(8, 16);
+  strictEqual(circle.x, 30);
+  strictEqual(circle.y, 22);
+  strictEqual(circle.radius, 12);
+
+  strictEqual((await image.detectCircles(1, 4294967295)).length > 0, true);
+  throws(() => image.detectCircles(16, 8), /minimum radius/);
+});
+
+test('detect lines', async () => {
+  const image = Image.blank(64, 48, 0x000000ff);
+  image.drawRectangle({ x: 12, y: 8, width: 40, height: 30 }, 0xffffffff, { filled: true });
+
+  const lines = await image.detectLines({ limit: 4 });
+  strictEqual(lines.map(({ angle, rho, votes }) => `${angle} ${Math.round(rho)} ${votes}`).join(', '), '90 8 40, 90 37 40, 0 12 30, 0 51 30');
+  strictEqual(`${Math.round(lines[0].start.y)} ${Math.round(lines[2].end.x)}`, '8 12');
+});
+
+test('decoded barcodes cover their bars', async () => {
+  // EAN-13 5901234123457, two pixels per module.
+  const modules = '10100010110100111011001100100110111101001110101010110011011011001000010101110010011101000100101';
+  const image = Image.blank(230, 50, 0xffffffff);
+  [...modules].forEach((module, i) => {
+    if (module === '1') {
+      image.drawRectangle({ x: 20 + i * 2, y: 10, width: 2, height: 30 }, 0x000000ff, { filled: true });
+    }
+  });
+
+  const [code] = await image.decodeCodes();
+  strictEqual(code.text, '5901234123457');
+  strictEqual(code.polygon.map(({ x, y }) => `${x},${y}`).join(' '), '20,10 209,10 209,39 20,39');
+});
+
+test('decoded QR codes cover their finder patterns', async () => {
+  // "herox" as a version 1 QR code.
+  const modules = [
+    '111111100101101111111',
+    '100000101101001000001',
+    '101110101100101011101',
+    '101110100101001011101',
+    '101110101000101011101',
+    '100000101001101000001',
+    '111111101010101111111',
+    '000000001111100000000',
+    '110100110110001110110',
+    '001010000000001000011',
+    '101010111010110001101',
+    '011001001101000001011',
+    '001110101100101011000',
+    '000000001001000111001',
+    '111111101010010101010',
+    '100000100001110110000',
+    '101110100101001110001',
+    '101110101011000101111',
+    '101110100100100011001',
+    '100000101100011110000',
+    '111111101101100000010',
+  ];
+  const drawCode = (turned: boolean) => {
+    const image = Image.blank(124, 124, 0xffffffff);
+    modules.forEach((row, y) => [...row].forEach((module, x) => {
+      if (module === '1') {
+        const [left, top] = turned ? [20 - y, x] : [x, y];
+        image.drawRectangle({ x: 20 + left * 4, y: 20 + top * 4, width: 4, height: 4 }, 0x000000ff, { filled: true });
+      }
+    }));
+    return image;
+  };
+  const corners = (code: { polygon: Array<{ x: number, y: number }> }) => code.polygon.map(({ x, y }) => `${x},${y}`).join(' ');
+
+  const [code] = await drawCode(false).decodeCodes();
+  strictEqual(code.text, 'herox');
+  strictEqual(corners(code), '20.5,20.5 104.5,20.5 104.5,104.5 20.5,104.5');
+  strictEqual(code.approximatePolygon, false);
+
+  // The corners start from the top-left as read, wherever that ends up.
+  const [turnedCode] = await drawCode(true).decodeCodes();
+  strictEqual(corners(turnedCode), '104.5,20.5 104.5,104.5 20.5,104.5 20.5,20.5');
+  strictEqual(turnedCode.approximatePolygon, false);
+});
+
+test('motion detection', async () => {
+  const detector = new MotionDetector();
+  const frame = Image.blank(100, 80, 0x000000ff);
+  strictEqual((await detector.update(frame)).length, 0);
+
+  frame.drawRectangle({ x: 10, y: 10, width: 5, height: 5 }, 0xffffffff, { filled: true });
+  frame.drawRectangle({ x: 17, y: 10, width: 3, height: 5 }, 0xffffffff, { filled: true });
+  frame.drawRectangle({ x: 70, y: 50, width: 2, height: 10 }, 0xffffffff, { filled: true });
+  const regions = await detector.update(frame);
+  strictEqual(regions.map(({ x, y, width, height, pixelCount }) => `${x},${y} ${width}x${height} ${pixelCount}`).join(' '), '10,10 10x5 40 70,50 2x10 20');
+
+  const [whole] = await detector.update(Image.blank(100, 80, 0xffffffff));
+  strictEqual(whole.pixelCount, 8000);
+});
+
+test('feature matches and their anchors', async () => {
+  const image = Image.blank(8, 8, 0x000000ff);
+  image.drawRectangle({ x: 4, y: 2, width: 2, height: 2 }, 0xffffffff, { filled: true });
+  const pixels = [0, 1, 2, 3].map(i => ({ x: i % 2, y: Math.floor(i / 2), rgba: 0xffffffff }));
+  const feature = { pixels, anchor: { x: -5, y: 1 } };
+
+  const [topLeft] = await image.findFeature(feature, 0, 0);
+  strictEqual(`${topLeft.x},${topLeft.y} ${topLeft.rgba}`, '4,2 4294967295');
+
+  const [location] = await image.locateFeature(feature, 0, 0);
+  strictEqual(`${location.width}x${location.height} ${location.anchorX},${location.anchorY}`, '2x2 -1,3');
+});
+
+test('subpixel feature search', async () => {
+  const grey = (value: number) => ((value << 24) | (value << 16) | (value << 8) | 0xff) >>> 0;
+  // A horizontal ramp, 16 levels per pixel.
+  const image = Image.blank(14, 3);
+  for (let x = 0; x < 14; x++) {
+    image.drawRectangle({ x, y: 0, width: 1, height: 3 }, grey(x * 16), { filled: true });
+  }
+  const ramp = (start: number) => ({ pixels: Array.from({ length: 9 }, (_, i) => ({ x: i % 3, y: Math.floor(i / 3), rgba: grey(start + (i % 3) * 16) })) });
+
+  strictEqual((await image.locateFeature(ramp(80), 0.06, 0)).map(({ x }) => x).join(' '), '4 5 6');
+  const exact = await image.locateFeature(ramp(80), 0.06, 0, { subpixel: true });
+  strictEqual(exact.map(({ subpixelX, subpixelY }) => `${subpixelX},${subpixelY}`).join(' '), '5,0');
+
+  // Half a pixel to the right of x = 5.
+  const shifted = await image.locateFeature(ramp(88), 0.06, 0, { subpixel: true });
+  strictEqual(shifted.length, 1);
+  strictEqual(Math.abs(shifted[0].subpixelX! - 5.5) < 1e-9, true);
+});
+
+test('rotated feature search', async () => {
+  // A 2x8 feature with a different colour in every pixel, so only the right
+  // orientation and position match.
+  const pixels = Array.from({ length: 16 }, (_, i) => ({ x: i % 2, y: Math.floor(i / 2), rgba: (((i + 1) * 15) << 24 | 0xff) >>> 0 }));
+  const describe = ({ x, y, width, height, angle, reflected }: FeatureLocation) => `${x},${y} ${width}x${height} ${angle}${reflected ? 'r' : ''}`;
+
+  const quarterTurned = Image.blank(20, 20, 0x000000ff);
+  for (const { x, y, rgba } of pixels) {
+    quarterTurned.drawRectangle({ x: 10 + 7 - y, y: 5 + x, width: 1, height: 1 }, rgba, { filled: true });
+  }
+  strictEqual((await quarterTurned.locateFeature({ pixels }, 0, 0, { minAngle: 90, maxAngle: 90 })).map(describe).join(' '), '10,5 8x2 90');
+  strictEqual((await quarterTurned.locateFeature({ pixels }, 0, 0, { minAngle: 0, maxAngle: 330, angleStep: 30, includeReflections: true })).map(describe).join(' '), '10,5 8x2 90');
+
+  const halfTurned = Image.blank(20, 20, 0x000000ff);
+  for (const { x, y, rgba } of pixels) {
+    halfTurned.drawRectangle({ x: 10 + 1 - x, y: 5 + 7 - y, width: 1, height: 1 }, rgba, { filled: true });
+  }
+  strictEqual((await halfTurned.locateFeature({ pixels }, 0, 0, { minAngle: 180, maxAngle: 180 })).map(describe).join(' '), '10,5 2x8 180');
+
+  // An 8x4 bar turned 30 degrees covers the same area, 32 pixels, inside an
+  // 8x8 box.
+  const bar = Array.from({ length: 32 }, (_, i) => ({ x: i % 8, y: Math.floor(i / 8), rgba: 0xffffffff }));
+  const [offAxis] = await Image.blank(12, 12, 0xffffffff).locateFeature({ pixels: bar }, 0, 0, { minAngle: 30, maxAngle: 30, firstOnly: true });
+  strictEqual(describe(offAxis), '0,0 8x8 30');
+  strictEqual((await Image.blank(12, 12, 0x000000ff).locateFeature({ pixels: bar }, 0, 0, { minAngle: 30, maxAngle: 30 })).length, 0);
+
+  throws(() => halfTurned.locateFeature({ pixels }, 0, 0, { maxAngle: 360, angleStep: 0.01 }), /more than 3600 angles/);
+});
+
+test('feature tracking', async () => {
+  const pixels = [0, 1, 2, 3].map(i => ({ x: i % 2, y: Math.floor(i / 2), rgba: 0xffffffff }));
+  const first = Image.blank(40, 30, 0x000000ff);
+  first.drawRectangle({ x: 10, y: 10, width: 2, height: 2 }, 0xffffffff, { filled: true });
+  const [location] = await first.locateFeature({ pixels }, 0, 0);
+  const tracker = new FeatureTracker({ pixels }, location, 0, 0);
+
+  const second = Image.blank(40, 30, 0x000000ff);
+  second.drawRectangle({ x: 14, y: 12, width: 2, height: 2 }, 0xffffffff, { filled: true });
+  const update = await tracker.update(second);
+  strictEqual(`${update.location?.x},${update.location?.y}`, '14,12');
+  strictEqual(`${update.velocityX},${update.velocityY}`, '4,2');
+  strictEqual(update.lostFrames, 0);
+});
+
+test('locating a shifted image', async () => {
+  const image = Image.blank(160, 120, 0x000000ff);
+  let state = 2463534242;
+  const next = () => {
+    state ^= state << 13;
+    state ^= state >>> 17;
+    state ^= state << 5;
+    return (state >>>= 0);
+  };
+  for (let i = 0; i < 60; i++) {
+    const [x, y, width, height, grey] = [next() % 150, next() % 110, 3 + next() % 15, 3 + next() % 15, next() & 0xff];
+    image.drawRectangle({ x, y, width, height }, ((grey << 24) | (grey << 16) | (grey << 8) | 0xff) >>> 0, { filled: true });
+  }
+
+  const reference = image.crop({ x: 40, y: 30, width: 80, height: 60 });
+  const location = await image.locateImage(reference, { keypoints: { pyramidLevels: 1 } });
+  const [, , x, , , y, , , h33] = location?.transform ?? [];
+  strictEqual(h33, 1);
+  strictEqual(Math.abs(x - 40) < 1, true);
+  strictEqual(Math.abs(y - 30) < 1, true);
+});
+
+test('golden image matching', async () => {
+  const directory = mkdtempSync(join(tmpdir(), 'herox-golden-'));
+  const golden = join(directory, 'button.png');
+  const image = Image.blank(4, 3, 0x000000ff);
+
+  await rejects(expectImageMatch(image, golden), /golden image missing/);
+
+  strictEqual((await expectImageMatch(image, golden, { update: true })).updated, true);
+  strictEqual((await expectImageMatch(image, golden)).matched, true);
+
+  image.drawRectangle({ x: 1, y: 1, width: 2, height: 1 }, 0xffffffff, { filled: true });
+  const result = await expectImageMatch(image, golden);
+  strictEqual(result.matched, false);
+  strictEqual(`${result.mismatchedPixels}/${result.totalPixels}`, '2/12');
+  strictEqual(JSON.stringify(result.mismatchBounds), '{"x":1,"y":1,"width":2,"height":1}');
+  strictEqual(existsSync(result.diffPath ?? ''), true);
+  // Failure images stay out of the golden's directory unless asked for.
+  strictEqual(result.diffPath, join(tmpdir(), 'herox-image-matches', 'button.diff.png'));
+  strictEqual(existsSync(join(directory, 'button.diff.png')), false);
+
+  const outputDirectory = join(directory, 'failures');
+  const kept = await expectImageMatch(image, golden, { outputDirectory });
+  strictEqual(kept.actualPath, join(outputDirectory, 'button.actual.png'));
+  strictEqual(existsSync(kept.actualPath ?? ''), true);
+});
+
+test('measuring a bordered gradient fill', async () => {
+  const image = Image.blank(22, 6, 0x808080ff);
+  image.drawRectangle({ x: 1, y: 1, width: 20, height: 4 }, 0x000000ff, { filled: true });
+  for (let x = 1; x <= 20; x++) {
+    // A gradient over the first half, fading out over the second.
+    const red = x <= 10 ? 255 - (x - 1) * 4 : Math.max(0, 219 - (x - 10) * 15);
+    image.drawRectangle({ x, y: 1, width: 1, height: 4 }, ((red << 24) | 0xff) >>> 0, { filled: true });
+  }
+
+  const region = { x: 0, y: 0, width: 22, height: 6 };
+  strictEqual(await image.measureFill(region, 0xff0000ff, FillDirection.LeftToRight, 0.05), 0.5);
+  strictEqual(await image.measureFill(region, 0xff0000ff, FillDirection.LeftToRight, 0.05, { followGradient: false }), 0.35);
+});
+
+test('measuring a thin bar with wide padding', async () => {
+  const image = Image.blank(40, 4, 0x808080ff);
+  image.drawRectangle({ x: 6, y: 0, width: 14, height: 4 }, 0xff0000ff, { filled: true });
+
+  const region = { x: 0, y: 0, width: 40, height: 4 };
+  strictEqual(await image.measureFill(region, 0xff0000ff, FillDirection.LeftToRight, 0.05), 0.5);
+  strictEqual(await Image.blank(40, 4, 0x808080ff).measureFill(region, 0xff0000ff, FillDirection.LeftToRight, 0.05), 0);
+});
+
+test('count-only searches', async () => {
+  const image = Image.blank(3, 2, 0xff0000ff);
+
+  strictEqual(await image.findRgbas(0xff0000ff, 0, { countOnly: true }), 6);
+  strictEqual(await image.findRgbas(0xff0000ff, 0, { countOnly: true, limit: 4 }), 4);
+  strictEqual(await image.findRgbas(0xff0000ff, 0, { countOnly: true, limit: 0 }), 0);
+});
+
+test('aborted searches', async () => {
+  const image = Image.blank(2000, 2000, 0xff0000ff);
+
+  const pixelsController = new AbortController();
+  const pixels = image.findRgbas(0xff0000ff, 0, undefined, pixelsController.signal);
+  pixelsController.abort();
+  await rejects(pixels, { name: 'AbortError' });
+
+  // Aborting once the search is under way stops it at its next checkpoint.
+  const pixelList = Array.from({ length: 2500 }, (_, i) => ({ x: i % 50, y: Math.floor(i / 50), rgba: 0xff0000ff }));
+  const featureController = new AbortController();
+  const features = image.findFeature({ pixels: pixelList }, 0, 0, undefined, featureController.signal);
+  await sleep(20);
+  featureController.abort();
+  await rejects(features, { name: 'AbortError' });
+});
+
+test('limited searches', async () => {
+  const image = Image.blank(3, 2, 0xff0000ff);
+  const positions = (pixels: Array<{ x: number, y: number }>) => pixels.map(({ x, y }) => `${x},${y}`).join(' ');
+
+  strictEqual(positions(await image.findRgbas(0xff0000ff, 0) as Array<Pixel>), '0,0 1,0 2,0 0,1 1,1 2,1');
+  strictEqual(positions(await image.findRgbas(0xff0000ff, 0, { limit: 2 }) as Array<Pixel>), '0,0 1,0');
+  strictEqual(positions(await image.findRgbas(0xff0000ff, 0, { firstOnly: true }) as Array<Pixel>), '0,0');
+  strictEqual((await image.findRgbas(0xff0000ff, 0, { limit: 0 }) as Array<Pixel>).length, 0);
+
+  const feature = { pixels: [{ x: 0, y: 0, rgba: 0xff0000ff }, { x: 1, y: 0, rgba: 0xff0000ff }] };
+  strictEqual(positions(await image.findFeature(feature, 0, 0) as Array<Pixel>), '0,0 1,0 0,1 1,1');
+  strictEqual(positions(await image.findFeature(feature, 0, 0, { limit: 3 }) as Array<Pixel>), '0,0 1,0 0,1');
+  strictEqual(positions(await image.findFeature(feature, 0, 0, { firstOnly: true }) as Array<Pixel>), '0,0');
+  strictEqual((await image.findFeature(feature, 0, 0, { limit: 0 }) as Array<Pixel>).length, 0);
+});